@@ -0,0 +1,204 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, Extension};
+use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use tempfile::NamedTempFile;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::services::{
+    cache::RenderCache,
+    chromium::{ChromiumService, GeneratePdfOptions, Length, PaperFormat},
+    markdown,
+};
+use crate::utils::multipart::FromStrField;
+
+use super::{
+    cached_conversion_output_response, conversion_error_response, internal_error_response,
+    lookup_render_cache, prepare_html_files, to_paper_size, validate_document_outline,
+    validation_error_response,
+};
+
+page_options_dto! {
+    #[derive(TryFromMultipart, Validate)]
+    #[try_from_multipart(rename_all = "camelCase")]
+    pub struct ConvertMarkdownDto {
+        files: Vec<FieldData<NamedTempFile>>,
+        template: Option<FieldData<NamedTempFile>>,
+
+        landscape: Option<bool>,
+        display_header_footer: Option<bool>,
+        print_background: Option<bool>,
+        #[validate(range(min = 0))]
+        scale: Option<f64>,
+        paper_format: Option<FromStrField<PaperFormat>>,
+        paper_width: Option<FromStrField<Length>>,
+        paper_height: Option<FromStrField<Length>>,
+        margin_top: Option<FromStrField<Length>>,
+        margin_bottom: Option<FromStrField<Length>>,
+        margin_left: Option<FromStrField<Length>>,
+        margin_right: Option<FromStrField<Length>>,
+        page_range: Option<String>,
+        header_template: Option<String>,
+        footer_template: Option<String>,
+        prefer_css_page_size: Option<bool>,
+        generate_tagged_pdf: Option<bool>,
+        generate_document_outline: Option<bool>,
+
+        no_cache: Option<bool>,
+    }
+}
+
+impl ConvertMarkdownDto {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = match Validate::validate(&self) {
+            Err(errors) => errors,
+            _ => ValidationErrors::new(),
+        };
+
+        let filenames = self
+            .files
+            .iter()
+            .filter_map(|file| file.metadata.file_name.as_deref())
+            .collect::<Vec<_>>();
+
+        if !filenames.iter().any(|filename| {
+            Path::new(filename)
+                .extension()
+                .is_some_and(|ext| ext == "md")
+        }) {
+            let error = ValidationError::new("files must contain at least one markdown file");
+            errors.add("files", error);
+        }
+
+        if filenames.contains(&"index.html") {
+            let error = ValidationError::new("files must not contain an index.html file");
+            errors.add("files", error);
+        }
+
+        if let Some(template) = &self.template {
+            match std::fs::read_to_string(template.contents.path()) {
+                Ok(template) if !template.contains(markdown::CONTENT_PLACEHOLDER) => {
+                    let error =
+                        ValidationError::new("template must contain a {{ content }} placeholder");
+                    errors.add("template", error);
+                }
+                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                    let error = ValidationError::new("template must be encoded in utf-8");
+                    errors.add("template", error);
+                }
+                _ => {}
+            }
+        }
+
+        validate_document_outline(
+            self.generate_tagged_pdf,
+            self.generate_document_outline,
+            &mut errors,
+        );
+
+        self.validate_page_options(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn to_generate_pdf_options(&self) -> GeneratePdfOptions {
+        let (paper_width, paper_height) = to_paper_size(
+            self.paper_format.as_ref().map(|format| format.0),
+            self.paper_width.as_ref().map(|width| width.0),
+            self.paper_height.as_ref().map(|height| height.0),
+        );
+
+        GeneratePdfOptions {
+            landscape: self.landscape,
+            display_header_footer: self.display_header_footer,
+            print_background: self.print_background,
+            scale: self.scale,
+            paper_width,
+            paper_height,
+            margin_top: self.margin_top.as_ref().map(|margin| margin.0.inches()),
+            margin_bottom: self.margin_bottom.as_ref().map(|margin| margin.0.inches()),
+            margin_left: self.margin_left.as_ref().map(|margin| margin.0.inches()),
+            margin_right: self.margin_right.as_ref().map(|margin| margin.0.inches()),
+            page_range: self.page_range.clone(),
+            header_template: self.header_template.clone(),
+            footer_template: self.footer_template.clone(),
+            prefer_css_page_size: self.prefer_css_page_size,
+            generate_tagged_pdf: self
+                .generate_tagged_pdf
+                .or(self.generate_document_outline.filter(|outline| *outline)),
+            generate_document_outline: self.generate_document_outline,
+        }
+    }
+}
+
+pub async fn convert_markdown(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+    Extension(render_cache): Extension<Arc<RenderCache>>,
+    TypedMultipart(dto): TypedMultipart<ConvertMarkdownDto>,
+) -> impl IntoResponse {
+    if let Err(err) = dto.validate() {
+        return validation_error_response(err);
+    }
+
+    let mut page_options = dto.to_page_options();
+    let options = dto.to_generate_pdf_options();
+
+    let template = match &dto.template {
+        Some(template) => match tokio::fs::read_to_string(template.contents.path()).await {
+            Ok(template) => Some(template),
+            Err(err) => return internal_error_response(err.into()),
+        },
+        None => None,
+    };
+
+    let cache_inputs = [
+        "markdown".to_string(),
+        format!("{template:?}"),
+        format!("{options:?}"),
+        format!("{page_options:?}"),
+    ];
+    let no_cache = dto.no_cache.unwrap_or(false) || dto.console_log.is_some();
+    let cache_key = match lookup_render_cache(
+        &render_cache,
+        no_cache,
+        &dto.files,
+        &cache_inputs,
+        "application/pdf",
+    )
+    .await
+    {
+        Ok(cache_key) => cache_key,
+        Err(response) => return response,
+    };
+
+    let (dir, index_url) = match prepare_html_files(dto.files).await {
+        Ok(prepared) => prepared,
+        Err(response) => return response,
+    };
+    page_options.local_directory = Some(dir.path().to_path_buf());
+
+    if let Err(err) = markdown::render_directory(dir.path(), template.as_deref()).await {
+        return internal_error_response(err);
+    }
+
+    let output_result = chromium_service
+        .generate_pdf_from_url(&index_url, &page_options, &options)
+        .await;
+
+    match output_result {
+        Ok(output) => cached_conversion_output_response(
+            &render_cache,
+            cache_key,
+            output,
+            "application/pdf",
+            dto.console_log,
+        ),
+        Err(err) => conversion_error_response(err),
+    }
+}
@@ -0,0 +1,712 @@
+#[macro_use]
+mod page_options;
+
+mod markdown;
+mod pdf;
+mod screenshot;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::body::{boxed, BoxBody, Bytes, Full, StreamBody};
+use axum::http::{header, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use axum_typed_multipart::{FieldData, TryFromField};
+use futures::stream::{self, StreamExt};
+use serde_json::json;
+use tempfile::{NamedTempFile, TempDir};
+use validator::{ValidationError, ValidationErrors};
+
+use crate::services::cache::{CacheKey, CacheKeyHasher, RenderCache};
+use crate::services::chromium::{
+    ConsoleMessage, ConversionData, ConversionError, ConversionOutput, DeviceMetrics, DevicePreset,
+    GeneratePdfOptions, Length, MediaFeature, MediaType, PageOptions, PaperFormat, ScriptTiming,
+};
+use crate::services::templates::{self, FOOTER_TEMPLATE_FILENAME, HEADER_TEMPLATE_FILENAME};
+use crate::utils::temp_files::group_temp_file_fields;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/url", post(pdf::convert_url))
+        .route("/url/screenshot", post(screenshot::screenshot_url))
+        .route("/html", post(pdf::convert_html))
+        .route("/html/screenshot", post(screenshot::screenshot_html))
+        .route("/markdown", post(markdown::convert_markdown))
+}
+
+#[derive(TryFromField, Clone, Copy)]
+#[try_from_field(rename_all = "lowercase")]
+pub enum MediaTypeDto {
+    Print,
+    Screen,
+}
+
+impl From<MediaTypeDto> for MediaType {
+    fn from(value: MediaTypeDto) -> Self {
+        match value {
+            MediaTypeDto::Print => Self::Print,
+            MediaTypeDto::Screen => Self::Screen,
+        }
+    }
+}
+
+#[derive(TryFromField, Clone, Copy)]
+#[try_from_field(rename_all = "camelCase")]
+pub enum ScriptTimingDto {
+    DocumentStart,
+    Load,
+}
+
+impl From<ScriptTimingDto> for ScriptTiming {
+    fn from(value: ScriptTimingDto) -> Self {
+        match value {
+            ScriptTimingDto::DocumentStart => Self::DocumentStart,
+            ScriptTimingDto::Load => Self::Load,
+        }
+    }
+}
+
+#[derive(TryFromField, Clone, Copy)]
+#[try_from_field(rename_all = "lowercase")]
+pub enum ConsoleLogDto {
+    /// Summarizes the console messages in the `X-Rustenberg-Console-Log` header.
+    Header,
+    /// Returns the console messages as a json part of a `multipart/mixed` response.
+    Part,
+}
+
+const CONSOLE_LOG_HEADER: &str = "x-rustenberg-console-log";
+
+/// Builds the response of a successful conversion, with the console log attached as requested.
+fn conversion_output_response(
+    output: ConversionOutput,
+    content_type: &'static str,
+    console_log: Option<ConsoleLogDto>,
+) -> Response {
+    match console_log {
+        None => (
+            [(header::CONTENT_TYPE, content_type)],
+            into_body(output.data),
+        )
+            .into_response(),
+        Some(ConsoleLogDto::Header) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (
+                    HeaderName::from_static(CONSOLE_LOG_HEADER),
+                    console_log_summary(&output.console_messages),
+                ),
+            ],
+            into_body(output.data),
+        )
+            .into_response(),
+        Some(ConsoleLogDto::Part) => match multipart_with_console_log(output, content_type) {
+            Ok(response) => response,
+            Err(err) => internal_error_response(err),
+        },
+    }
+}
+
+const CACHE_HEADER: &str = "x-rustenberg-cache";
+
+/// Looks up the document rendered from the uploaded files and the other inputs of the conversion
+/// in the render cache. A cached document is returned as the error, so that it is sent as is.
+/// Otherwise, returns the key the rendered document must be stored under, unless the cache is
+/// disabled or bypassed.
+async fn lookup_render_cache(
+    render_cache: &RenderCache,
+    no_cache: bool,
+    files: &[FieldData<NamedTempFile>],
+    inputs: &[String],
+    content_type: &'static str,
+) -> Result<Option<CacheKey>, Response> {
+    if !render_cache.is_enabled() || no_cache {
+        return Ok(None);
+    }
+
+    let mut files: Vec<_> = files.iter().collect();
+    files.sort_by(|file1, file2| file1.metadata.file_name.cmp(&file2.metadata.file_name));
+
+    let mut hasher = CacheKeyHasher::default();
+    for file in files {
+        let content = match tokio::fs::read(file.contents.path()).await {
+            Ok(content) => content,
+            Err(err) => return Err(internal_error_response(err.into())),
+        };
+
+        hasher.update(file.metadata.file_name.as_deref().unwrap_or_default());
+        hasher.update(content);
+    }
+    for input in inputs {
+        hasher.update(input);
+    }
+    let cache_key = hasher.finish();
+
+    match render_cache.get(&cache_key).await {
+        Some(data) => Err((
+            [
+                (header::CONTENT_TYPE, content_type),
+                (HeaderName::from_static(CACHE_HEADER), "hit"),
+            ],
+            data,
+        )
+            .into_response()),
+        None => Ok(Some(cache_key)),
+    }
+}
+
+/// Same as [conversion_output_response], with the output stored in the render cache under the
+/// key, if any.
+fn cached_conversion_output_response(
+    render_cache: &Arc<RenderCache>,
+    cache_key: Option<CacheKey>,
+    mut output: ConversionOutput,
+    content_type: &'static str,
+    console_log: Option<ConsoleLogDto>,
+) -> Response {
+    if let Some(cache_key) = cache_key {
+        output.data = render_cache.store(cache_key, output.data);
+    }
+
+    let mut response = conversion_output_response(output, content_type, console_log);
+    if render_cache.is_enabled() {
+        response.headers_mut().insert(
+            HeaderName::from_static(CACHE_HEADER),
+            HeaderValue::from_static("miss"),
+        );
+    }
+
+    response
+}
+
+/// Counts the console messages by level, formatted as `error=1, log=3`. Levels are listed in the
+/// order they were first logged.
+fn console_log_summary(console_messages: &[ConsoleMessage]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for message in console_messages {
+        match counts.iter_mut().find(|(level, _)| *level == message.level) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&message.level, 1)),
+        }
+    }
+
+    counts
+        .iter()
+        .map(|(level, count)| format!("{level}={count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn into_body(data: ConversionData) -> BoxBody {
+    match data {
+        ConversionData::Buffered(data) => boxed(Full::from(data)),
+        ConversionData::Streamed(stream) => boxed(StreamBody::new(stream)),
+    }
+}
+
+/// Builds a `multipart/mixed` response, whose first part is the output of the conversion and
+/// second part is the console log. The output is streamed into its part, if it is streamed.
+fn multipart_with_console_log(
+    output: ConversionOutput,
+    content_type: &str,
+) -> anyhow::Result<Response> {
+    let boundary = uuid::Uuid::new_v4().simple().to_string();
+    let console_log = serde_json::to_vec(&json!({ "consoleMessages": output.console_messages }))?;
+
+    let part_headers = |part_content_type: &str, name: &str| {
+        format!(
+            "--{boundary}\r\n\
+             Content-Type: {part_content_type}\r\n\
+             Content-Disposition: inline; name=\"{name}\"\r\n\r\n"
+        )
+    };
+
+    let mut console_log_part =
+        format!("\r\n{}", part_headers("application/json", "console-log")).into_bytes();
+    console_log_part.extend_from_slice(&console_log);
+    console_log_part.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let output_part_headers = Bytes::from(part_headers(content_type, "output"));
+    let body = stream::once(async { Ok(output_part_headers) })
+        .chain(output.data.into_stream())
+        .chain(stream::once(async { Ok(Bytes::from(console_log_part)) }));
+
+    let headers = [(
+        header::CONTENT_TYPE,
+        format!("multipart/mixed; boundary={boundary}"),
+    )];
+
+    Ok((headers, StreamBody::new(body)).into_response())
+}
+
+fn validation_error_response(err: ValidationErrors) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": err.to_string() })),
+    )
+        .into_response()
+}
+
+fn internal_error_response(err: anyhow::Error) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": err.to_string() })),
+    )
+        .into_response()
+}
+
+fn conversion_error_response(err: anyhow::Error) -> Response {
+    match err.downcast_ref::<ConversionError>() {
+        Some(ConversionError::QueueFull { retry_after }) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after.as_secs().to_string())],
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        Some(ConversionError::BrowserUnavailable) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        Some(ConversionError::WaitTimeout { .. }) => (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        Some(ConversionError::HttpStatus { status }) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "error": err.to_string(), "status": status })),
+        )
+            .into_response(),
+        Some(ConversionError::ConsoleErrors { messages }) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": err.to_string(), "consoleMessages": messages })),
+        )
+            .into_response(),
+        Some(ConversionError::ResourceLoadingFailed { resources }) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "error": err.to_string(), "failedResources": resources })),
+        )
+            .into_response(),
+        Some(ConversionError::UrlNotAllowed { .. }) => (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        Some(ConversionError::InjectionFailed { errors }) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": err.to_string(), "scriptErrors": errors })),
+        )
+            .into_response(),
+        Some(ConversionError::InvalidOption { .. }) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+        None => internal_error_response(err),
+    }
+}
+
+/// The document outline is built from the structure of a tagged pdf, which it therefore requires.
+fn validate_document_outline(
+    generate_tagged_pdf: Option<bool>,
+    generate_document_outline: Option<bool>,
+    errors: &mut ValidationErrors,
+) {
+    if generate_document_outline == Some(true) && generate_tagged_pdf == Some(false) {
+        let error = ValidationError::new(
+            "generate_document_outline requires generate_tagged_pdf to not be disabled",
+        );
+        errors.add("generate_document_outline", error);
+    }
+}
+
+fn validate_page_load_time(
+    min_page_load_time_ms: Option<u64>,
+    max_page_load_time_ms: Option<u64>,
+    errors: &mut ValidationErrors,
+) {
+    match (min_page_load_time_ms, max_page_load_time_ms) {
+        (Some(min_page_load_time_ms), Some(max_page_load_time_ms))
+            if max_page_load_time_ms < min_page_load_time_ms =>
+        {
+            let error = ValidationError::new(
+                "min_page_load_time_min must be less than max_page_load_time_ms",
+            );
+
+            errors.add("min_page_load_time_ms", error);
+        }
+        _ => {}
+    };
+}
+
+fn validate_extra_http_headers(
+    extra_http_headers: Option<&HashMap<String, String>>,
+    errors: &mut ValidationErrors,
+) {
+    let Some(extra_http_headers) = extra_http_headers else {
+        return;
+    };
+
+    for (name, value) in extra_http_headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err()
+        {
+            let error = ValidationError::new("extra_http_headers must contain valid http headers");
+            errors.add("extra_http_headers", error);
+            return;
+        }
+    }
+}
+
+fn validate_user_agent_override(
+    user_agent: Option<&str>,
+    accept_language: Option<&str>,
+    errors: &mut ValidationErrors,
+) {
+    let is_invalid = |value: Option<&str>| value.is_some_and(|v| HeaderValue::from_str(v).is_err());
+
+    if is_invalid(user_agent) {
+        let error = ValidationError::new("user_agent must be a valid http header value");
+        errors.add("user_agent", error);
+    }
+
+    if is_invalid(accept_language) {
+        let error = ValidationError::new("accept_language must be a valid http header value");
+        errors.add("accept_language", error);
+    }
+}
+
+fn validate_emulated_media_features(
+    emulated_media_features: Option<&Vec<MediaFeature>>,
+    errors: &mut ValidationErrors,
+) {
+    let Some(emulated_media_features) = emulated_media_features else {
+        return;
+    };
+
+    if emulated_media_features
+        .iter()
+        .any(|feature| feature.name.trim().is_empty())
+    {
+        let error = ValidationError::new("emulated_media_features must have a name");
+        errors.add("emulated_media_features", error);
+    }
+}
+
+/// Metrics of the emulated device, from the device preset, with the given metrics overriding the
+/// preset's ones. Returns `None` when none of them is given.
+fn to_device_metrics(
+    device: Option<DevicePreset>,
+    viewport_width: Option<i64>,
+    viewport_height: Option<i64>,
+    device_scale_factor: Option<f64>,
+    is_mobile: Option<bool>,
+) -> Option<DeviceMetrics> {
+    if device.is_none()
+        && viewport_width.is_none()
+        && viewport_height.is_none()
+        && device_scale_factor.is_none()
+        && is_mobile.is_none()
+    {
+        return None;
+    }
+
+    let metrics = device.map(|device| device.metrics()).unwrap_or_default();
+
+    Some(DeviceMetrics {
+        width: viewport_width.unwrap_or(metrics.width),
+        height: viewport_height.unwrap_or(metrics.height),
+        device_scale_factor: device_scale_factor.unwrap_or(metrics.device_scale_factor),
+        mobile: is_mobile.unwrap_or(metrics.mobile),
+    })
+}
+
+/// Width and height of the paper in inches, from the paper format, with the given width and
+/// height overriding the format's ones.
+fn to_paper_size(
+    format: Option<PaperFormat>,
+    width: Option<Length>,
+    height: Option<Length>,
+) -> (Option<f64>, Option<f64>) {
+    let (format_width, format_height) = match format.map(|format| format.size()) {
+        Some((width, height)) => (Some(width), Some(height)),
+        None => (None, None),
+    };
+
+    (
+        width.or(format_width).map(|width| width.inches()),
+        height.or(format_height).map(|height| height.inches()),
+    )
+}
+
+fn injected_file_extension(file: &FieldData<NamedTempFile>) -> Option<&str> {
+    let filename = file.metadata.file_name.as_deref()?;
+    Path::new(filename).extension()?.to_str()
+}
+
+fn validate_injected_files(files: &[FieldData<NamedTempFile>], errors: &mut ValidationErrors) {
+    let are_valid = files
+        .iter()
+        .all(|file| matches!(injected_file_extension(file), Some("css" | "js")));
+
+    if !are_valid {
+        let error = ValidationError::new("files must be .css or .js files");
+        errors.add("files", error);
+    }
+}
+
+/// Same as [`validate_injected_files`], but also accepts the header and footer templates and the
+/// images and fonts they may reference.
+fn validate_injected_and_template_files(
+    files: &[FieldData<NamedTempFile>],
+    errors: &mut ValidationErrors,
+) {
+    let are_valid = files.iter().all(|file| {
+        let filename = file.metadata.file_name.as_deref();
+        filename == Some(HEADER_TEMPLATE_FILENAME)
+            || filename == Some(FOOTER_TEMPLATE_FILENAME)
+            || matches!(
+                injected_file_extension(file),
+                Some(
+                    "css"
+                        | "js"
+                        | "png"
+                        | "jpg"
+                        | "jpeg"
+                        | "gif"
+                        | "svg"
+                        | "webp"
+                        | "woff"
+                        | "woff2"
+                        | "ttf"
+                        | "otf"
+                )
+            )
+    });
+
+    if !are_valid {
+        let error = ValidationError::new(
+            "files must be .css or .js files, header.html, footer.html, images or fonts",
+        );
+        errors.add("files", error);
+    }
+}
+
+/// The header and footer templates can be given either as fields or as uploaded files.
+fn validate_template_files(
+    files: &[FieldData<NamedTempFile>],
+    header_template: Option<&str>,
+    footer_template: Option<&str>,
+    errors: &mut ValidationErrors,
+) {
+    let is_uploaded = |name| {
+        files
+            .iter()
+            .any(|file| file.metadata.file_name.as_deref() == Some(name))
+    };
+
+    if header_template.is_some() && is_uploaded(HEADER_TEMPLATE_FILENAME) {
+        let error = ValidationError::new("header_template must not be given with a header.html");
+        errors.add("header_template", error);
+    }
+
+    if footer_template.is_some() && is_uploaded(FOOTER_TEMPLATE_FILENAME) {
+        let error = ValidationError::new("footer_template must not be given with a footer.html");
+        errors.add("footer_template", error);
+    }
+}
+
+/// Header and footer templates uploaded as `header.html` and `footer.html`, with the resources
+/// they reference among the other uploaded files inlined.
+#[derive(Default)]
+struct UploadedTemplates {
+    header: Option<String>,
+    footer: Option<String>,
+    /// Filenames of the templates and of the files inlined into them.
+    used_files: Vec<String>,
+}
+
+/// Template used in place of the header or footer that was not uploaded nor given as a field,
+/// since chromium otherwise prints its default one.
+const EMPTY_TEMPLATE: &str = "<span></span>";
+
+impl UploadedTemplates {
+    /// Uses the templates as the header and footer of the pdf, which are then displayed unless
+    /// explicitly disabled.
+    fn apply(self, options: &mut GeneratePdfOptions) {
+        if self.header.is_none() && self.footer.is_none() {
+            return;
+        }
+
+        options.display_header_footer = options.display_header_footer.or(Some(true));
+        options.header_template = self
+            .header
+            .or(options.header_template.take())
+            .or_else(|| Some(EMPTY_TEMPLATE.to_owned()));
+        options.footer_template = self
+            .footer
+            .or(options.footer_template.take())
+            .or_else(|| Some(EMPTY_TEMPLATE.to_owned()));
+    }
+}
+
+async fn read_uploaded_templates(
+    files: &[FieldData<NamedTempFile>],
+) -> Result<UploadedTemplates, Response> {
+    let files_by_name = files
+        .iter()
+        .filter_map(|file| {
+            let filename = file.metadata.file_name.clone()?;
+            Some((filename, file.contents.path().to_path_buf()))
+        })
+        .collect::<HashMap<String, PathBuf>>();
+
+    let mut uploaded_templates = UploadedTemplates::default();
+    for filename in [HEADER_TEMPLATE_FILENAME, FOOTER_TEMPLATE_FILENAME] {
+        let Some(path) = files_by_name.get(filename) else {
+            continue;
+        };
+
+        let inlined_template = match tokio::fs::read_to_string(path).await {
+            Ok(template) => templates::inline_resources(&template, &files_by_name).await,
+            Err(err) => Err(err.into()),
+        };
+        let inlined_template = match inlined_template {
+            Ok(inlined_template) => inlined_template,
+            Err(err) => return Err(internal_error_response(err)),
+        };
+
+        if filename == HEADER_TEMPLATE_FILENAME {
+            uploaded_templates.header = Some(inlined_template.html);
+        } else {
+            uploaded_templates.footer = Some(inlined_template.html);
+        }
+        uploaded_templates.used_files.push(filename.to_owned());
+        uploaded_templates
+            .used_files
+            .extend(inlined_template.inlined_files);
+    }
+
+    Ok(uploaded_templates)
+}
+
+/// Adds the uploaded stylesheets and scripts to the injections of the page, in their filename's
+/// alphabetical order, after the ones given as fields. The files used by the header and footer
+/// templates are not injected.
+async fn read_injected_files(
+    files: &[FieldData<NamedTempFile>],
+    used_files: &[String],
+    page_options: &mut PageOptions,
+) -> Result<(), Response> {
+    let mut files: Vec<_> = files
+        .iter()
+        .filter(|file| matches!(injected_file_extension(file), Some("css" | "js")))
+        .filter(|file| {
+            file.metadata
+                .file_name
+                .as_ref()
+                .is_none_or(|filename| !used_files.contains(filename))
+        })
+        .collect();
+    files.sort_by(|file1, file2| file1.metadata.file_name.cmp(&file2.metadata.file_name));
+
+    for file in files {
+        let content = match tokio::fs::read_to_string(file.contents.path()).await {
+            Ok(content) => content,
+            Err(err) => return Err(internal_error_response(err.into())),
+        };
+
+        match injected_file_extension(file) {
+            Some("css") => page_options.inject_css.push(content),
+            _ => page_options.inject_js.push(content),
+        }
+    }
+
+    Ok(())
+}
+
+/// Groups the uploaded html files into their own directory, and returns the directory along with
+/// the url of its `index.html` file. The directory is deleted once dropped.
+async fn prepare_html_files(
+    files: Vec<FieldData<NamedTempFile>>,
+) -> Result<(TempDir, String), Response> {
+    let dir = match group_temp_file_fields(files).await {
+        Ok(dir) => dir,
+        Err(err) => return Err(Json(json!({ "error": err.to_string() })).into_response()),
+    };
+
+    let index_url = match dir.path().to_str() {
+        Some(path) => format!("file://{path}/index.html"),
+        None => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "could not get directory path" })),
+            )
+                .into_response())
+        }
+    };
+
+    Ok((dir, index_url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploaded_footer_is_applied_with_an_empty_header() {
+        let templates = UploadedTemplates {
+            footer: Some("<div>footer</div>".to_owned()),
+            ..Default::default()
+        };
+        let mut options = GeneratePdfOptions::default();
+
+        templates.apply(&mut options);
+
+        assert_eq!(options.display_header_footer, Some(true));
+        assert_eq!(options.header_template.as_deref(), Some(EMPTY_TEMPLATE));
+        assert_eq!(
+            options.footer_template.as_deref(),
+            Some("<div>footer</div>")
+        );
+    }
+
+    #[test]
+    fn uploaded_footer_keeps_the_header_given_as_a_field() {
+        let templates = UploadedTemplates {
+            footer: Some("<div>footer</div>".to_owned()),
+            ..Default::default()
+        };
+        let mut options = GeneratePdfOptions {
+            display_header_footer: Some(false),
+            header_template: Some("<div>header</div>".to_owned()),
+            ..Default::default()
+        };
+
+        templates.apply(&mut options);
+
+        assert_eq!(options.display_header_footer, Some(false));
+        assert_eq!(
+            options.header_template.as_deref(),
+            Some("<div>header</div>")
+        );
+        assert_eq!(
+            options.footer_template.as_deref(),
+            Some("<div>footer</div>")
+        );
+    }
+
+    #[test]
+    fn options_are_unchanged_without_uploaded_templates() {
+        let mut options = GeneratePdfOptions::default();
+
+        UploadedTemplates::default().apply(&mut options);
+
+        assert_eq!(options.display_header_footer, None);
+        assert_eq!(options.header_template, None);
+        assert_eq!(options.footer_template, None);
+    }
+}
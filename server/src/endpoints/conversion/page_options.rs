@@ -0,0 +1,212 @@
+/// Defines a conversion DTO along with the fields of the page options shared by every endpoint,
+/// and generates its `to_page_options` and `validate_page_options` methods. DTOs of the url
+/// endpoints are marked as `remote`, which adds the options that only apply to remote pages:
+/// extra http headers, cookies, failing on http status codes and injections.
+///
+/// The fields specific to the endpoint are given in the struct, each followed by a comma.
+macro_rules! page_options_dto {
+    (@struct $(#[$attr:meta])* $name:ident { $($fields:tt)* }) => {
+        $(#[$attr])*
+        pub struct $name {
+            $($fields)*
+
+            #[validate(range(min = 0, max = 10000))]
+            min_page_load_time_ms: Option<u64>,
+            #[validate(range(min = 0, max = 10000))]
+            max_page_load_time_ms: Option<u64>,
+
+            emulated_media_type: Option<$crate::endpoints::conversion::MediaTypeDto>,
+            emulated_media_features: Option<
+                $crate::utils::multipart::JsonField<Vec<$crate::services::chromium::MediaFeature>>,
+            >,
+
+            #[validate(length(min = 1))]
+            wait_for_selector: Option<String>,
+            #[validate(length(min = 1))]
+            wait_for_expression: Option<String>,
+
+            fail_on_console_errors: Option<bool>,
+            console_log: Option<$crate::endpoints::conversion::ConsoleLogDto>,
+
+            fail_on_resource_loading_failed: Option<bool>,
+            ignored_resource_loading_failures: Option<
+                $crate::utils::multipart::JsonField<Vec<$crate::services::chromium::UrlPattern>>,
+            >,
+
+            blocked_resource_types: Option<
+                $crate::utils::multipart::FromStrField<$crate::services::chromium::ResourceTypes>,
+            >,
+            blocked_urls: Option<
+                $crate::utils::multipart::JsonField<Vec<$crate::services::chromium::UrlPattern>>,
+            >,
+            skip_default_blocklist: Option<bool>,
+
+            device: Option<
+                $crate::utils::multipart::FromStrField<$crate::services::chromium::DevicePreset>,
+            >,
+            #[validate(range(min = 1, max = 10000))]
+            viewport_width: Option<i64>,
+            #[validate(range(min = 1, max = 10000))]
+            viewport_height: Option<i64>,
+            #[validate(range(min = 0.1, max = 10))]
+            device_scale_factor: Option<f64>,
+            is_mobile: Option<bool>,
+
+            #[validate(length(min = 1))]
+            timezone: Option<String>,
+            #[validate(length(min = 1))]
+            locale: Option<String>,
+            #[validate(length(min = 1))]
+            accept_language: Option<String>,
+            #[validate(length(min = 1))]
+            user_agent: Option<String>,
+        }
+    };
+
+    (@page_options $dto:ident) => {
+        $crate::services::chromium::PageOptions {
+            min_page_load_time_ms: $dto.min_page_load_time_ms,
+            max_page_load_time_ms: $dto.max_page_load_time_ms,
+            emulated_media_type: $dto.emulated_media_type.map(Into::into),
+            emulated_media_features: $dto
+                .emulated_media_features
+                .as_ref()
+                .map(|features| features.0.clone())
+                .unwrap_or_default(),
+            wait_for_selector: $dto.wait_for_selector.clone(),
+            wait_for_expression: $dto.wait_for_expression.clone(),
+            fail_on_console_errors: $dto.fail_on_console_errors.unwrap_or(false),
+            fail_on_resource_loading_failed: $dto.fail_on_resource_loading_failed.unwrap_or(false),
+            ignored_resource_loading_failures: $dto
+                .ignored_resource_loading_failures
+                .as_ref()
+                .map(|patterns| patterns.0.clone())
+                .unwrap_or_default(),
+            blocked_resource_types: $dto
+                .blocked_resource_types
+                .as_ref()
+                .map(|resource_types| resource_types.0.clone())
+                .unwrap_or_default(),
+            blocked_urls: $dto
+                .blocked_urls
+                .as_ref()
+                .map(|patterns| patterns.0.clone())
+                .unwrap_or_default(),
+            skip_default_blocklist: $dto.skip_default_blocklist.unwrap_or(false),
+            device_metrics: $crate::endpoints::conversion::to_device_metrics(
+                $dto.device.as_ref().map(|device| device.0),
+                $dto.viewport_width,
+                $dto.viewport_height,
+                $dto.device_scale_factor,
+                $dto.is_mobile,
+            ),
+            timezone: $dto.timezone.clone(),
+            locale: $dto.locale.clone(),
+            accept_language: $dto.accept_language.clone(),
+            user_agent: $dto.user_agent.clone(),
+            ..Default::default()
+        }
+    };
+
+    (@validate $dto:ident, $errors:ident) => {
+        $crate::endpoints::conversion::validate_page_load_time(
+            $dto.min_page_load_time_ms,
+            $dto.max_page_load_time_ms,
+            $errors,
+        );
+        $crate::endpoints::conversion::validate_emulated_media_features(
+            $dto.emulated_media_features
+                .as_ref()
+                .map(|features| &features.0),
+            $errors,
+        );
+        $crate::endpoints::conversion::validate_user_agent_override(
+            $dto.user_agent.as_deref(),
+            $dto.accept_language.as_deref(),
+            $errors,
+        );
+    };
+
+    (
+        remote
+        $(#[$attr:meta])*
+        pub struct $name:ident { $($fields:tt)* }
+    ) => {
+        page_options_dto!(@struct $(#[$attr])* $name {
+            $($fields)*
+
+            extra_http_headers: Option<
+                $crate::utils::multipart::JsonField<std::collections::HashMap<String, String>>,
+            >,
+            extra_http_headers_origin_only: Option<bool>,
+            cookies: Option<
+                $crate::utils::multipart::JsonField<Vec<$crate::services::chromium::Cookie>>,
+            >,
+
+            fail_on_http_status_codes: Option<
+                $crate::utils::multipart::FromStrField<$crate::services::chromium::HttpStatusCodes>,
+            >,
+
+            inject_css: Option<String>,
+            inject_js: Option<String>,
+            inject_js_timing: Option<$crate::endpoints::conversion::ScriptTimingDto>,
+        });
+
+        impl $name {
+            fn to_page_options(&self) -> $crate::services::chromium::PageOptions {
+                $crate::services::chromium::PageOptions {
+                    extra_http_headers: self
+                        .extra_http_headers
+                        .as_ref()
+                        .map(|headers| headers.0.clone())
+                        .unwrap_or_default(),
+                    extra_http_headers_origin_only: self
+                        .extra_http_headers_origin_only
+                        .unwrap_or(false),
+                    cookies: self
+                        .cookies
+                        .as_ref()
+                        .map(|cookies| cookies.0.clone())
+                        .unwrap_or_default(),
+                    fail_on_http_status_codes: self
+                        .fail_on_http_status_codes
+                        .as_ref()
+                        .map(|codes| codes.0.clone())
+                        .unwrap_or_default(),
+                    inject_css: self.inject_css.iter().cloned().collect(),
+                    inject_js: self.inject_js.iter().cloned().collect(),
+                    inject_js_timing: self
+                        .inject_js_timing
+                        .map($crate::services::chromium::ScriptTiming::from)
+                        .unwrap_or_default(),
+                    ..page_options_dto!(@page_options self)
+                }
+            }
+
+            fn validate_page_options(&self, errors: &mut validator::ValidationErrors) {
+                $crate::endpoints::conversion::validate_extra_http_headers(
+                    self.extra_http_headers.as_ref().map(|headers| &headers.0),
+                    errors,
+                );
+                page_options_dto!(@validate self, errors);
+            }
+        }
+    };
+
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident { $($fields:tt)* }
+    ) => {
+        page_options_dto!(@struct $(#[$attr])* $name { $($fields)* });
+
+        impl $name {
+            fn to_page_options(&self) -> $crate::services::chromium::PageOptions {
+                page_options_dto!(@page_options self)
+            }
+
+            fn validate_page_options(&self, errors: &mut validator::ValidationErrors) {
+                page_options_dto!(@validate self, errors);
+            }
+        }
+    };
+}
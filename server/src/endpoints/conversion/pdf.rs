@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, Extension};
+use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use tempfile::NamedTempFile;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::services::cache::RenderCache;
+use crate::services::chromium::{ChromiumService, GeneratePdfOptions, Length, PaperFormat};
+use crate::utils::multipart::FromStrField;
+
+use super::{
+    cached_conversion_output_response, conversion_error_response, conversion_output_response,
+    lookup_render_cache, prepare_html_files, read_injected_files, read_uploaded_templates,
+    to_paper_size, validate_document_outline, validate_injected_and_template_files,
+    validate_template_files, validation_error_response,
+};
+
+page_options_dto! {
+    remote
+    #[derive(TryFromMultipart, Validate)]
+    #[try_from_multipart(rename_all = "camelCase")]
+    pub struct ConvertUrlDto {
+        #[validate(url)]
+        url: String,
+        landscape: Option<bool>,
+        display_header_footer: Option<bool>,
+        print_background: Option<bool>,
+        #[validate(range(min = 0))]
+        scale: Option<f64>,
+        paper_format: Option<FromStrField<PaperFormat>>,
+        paper_width: Option<FromStrField<Length>>,
+        paper_height: Option<FromStrField<Length>>,
+        margin_top: Option<FromStrField<Length>>,
+        margin_bottom: Option<FromStrField<Length>>,
+        margin_left: Option<FromStrField<Length>>,
+        margin_right: Option<FromStrField<Length>>,
+        page_range: Option<String>,
+        header_template: Option<String>,
+        footer_template: Option<String>,
+        prefer_css_page_size: Option<bool>,
+        generate_tagged_pdf: Option<bool>,
+        generate_document_outline: Option<bool>,
+
+        files: Vec<FieldData<NamedTempFile>>,
+    }
+}
+
+impl ConvertUrlDto {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = match Validate::validate(&self) {
+            Err(errors) => errors,
+            _ => ValidationErrors::new(),
+        };
+
+        validate_document_outline(
+            self.generate_tagged_pdf,
+            self.generate_document_outline,
+            &mut errors,
+        );
+
+        validate_injected_and_template_files(&self.files, &mut errors);
+        validate_template_files(
+            &self.files,
+            self.header_template.as_deref(),
+            self.footer_template.as_deref(),
+            &mut errors,
+        );
+
+        self.validate_page_options(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn to_generate_pdf_options(&self) -> GeneratePdfOptions {
+        let (paper_width, paper_height) = to_paper_size(
+            self.paper_format.as_ref().map(|format| format.0),
+            self.paper_width.as_ref().map(|width| width.0),
+            self.paper_height.as_ref().map(|height| height.0),
+        );
+
+        GeneratePdfOptions {
+            landscape: self.landscape,
+            display_header_footer: self.display_header_footer,
+            print_background: self.print_background,
+            scale: self.scale,
+            paper_width,
+            paper_height,
+            margin_top: self.margin_top.as_ref().map(|margin| margin.0.inches()),
+            margin_bottom: self.margin_bottom.as_ref().map(|margin| margin.0.inches()),
+            margin_left: self.margin_left.as_ref().map(|margin| margin.0.inches()),
+            margin_right: self.margin_right.as_ref().map(|margin| margin.0.inches()),
+            page_range: self.page_range.clone(),
+            header_template: self.header_template.clone(),
+            footer_template: self.footer_template.clone(),
+            prefer_css_page_size: self.prefer_css_page_size,
+            generate_tagged_pdf: self
+                .generate_tagged_pdf
+                .or(self.generate_document_outline.filter(|outline| *outline)),
+            generate_document_outline: self.generate_document_outline,
+        }
+    }
+}
+
+pub async fn convert_url(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+    TypedMultipart(dto): TypedMultipart<ConvertUrlDto>,
+) -> impl IntoResponse {
+    if let Err(err) = dto.validate() {
+        return validation_error_response(err);
+    }
+
+    let mut page_options = dto.to_page_options();
+    let mut options = dto.to_generate_pdf_options();
+
+    let uploaded_templates = match read_uploaded_templates(&dto.files).await {
+        Ok(uploaded_templates) => uploaded_templates,
+        Err(response) => return response,
+    };
+    let used_files = &uploaded_templates.used_files;
+    if let Err(response) = read_injected_files(&dto.files, used_files, &mut page_options).await {
+        return response;
+    }
+    uploaded_templates.apply(&mut options);
+
+    let output_result = chromium_service
+        .generate_pdf_from_url(&dto.url, &page_options, &options)
+        .await;
+
+    match output_result {
+        Ok(output) => conversion_output_response(output, "application/pdf", dto.console_log),
+        Err(err) => conversion_error_response(err),
+    }
+}
+
+page_options_dto! {
+    #[derive(TryFromMultipart, Validate)]
+    #[try_from_multipart(rename_all = "camelCase")]
+    pub struct ConvertHtmlDto {
+        files: Vec<FieldData<NamedTempFile>>,
+
+        landscape: Option<bool>,
+        display_header_footer: Option<bool>,
+        print_background: Option<bool>,
+        #[validate(range(min = 0))]
+        scale: Option<f64>,
+        paper_format: Option<FromStrField<PaperFormat>>,
+        paper_width: Option<FromStrField<Length>>,
+        paper_height: Option<FromStrField<Length>>,
+        margin_top: Option<FromStrField<Length>>,
+        margin_bottom: Option<FromStrField<Length>>,
+        margin_left: Option<FromStrField<Length>>,
+        margin_right: Option<FromStrField<Length>>,
+        page_range: Option<String>,
+        header_template: Option<String>,
+        footer_template: Option<String>,
+        prefer_css_page_size: Option<bool>,
+        generate_tagged_pdf: Option<bool>,
+        generate_document_outline: Option<bool>,
+
+        no_cache: Option<bool>,
+    }
+}
+
+impl ConvertHtmlDto {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = match Validate::validate(&self) {
+            Err(errors) => errors,
+            _ => ValidationErrors::new(),
+        };
+
+        if self.files.is_empty() {
+            let error = ValidationError::new("files must not be empty");
+            errors.add("files", error);
+        }
+        validate_template_files(
+            &self.files,
+            self.header_template.as_deref(),
+            self.footer_template.as_deref(),
+            &mut errors,
+        );
+
+        validate_document_outline(
+            self.generate_tagged_pdf,
+            self.generate_document_outline,
+            &mut errors,
+        );
+
+        self.validate_page_options(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn to_generate_pdf_options(&self) -> GeneratePdfOptions {
+        let (paper_width, paper_height) = to_paper_size(
+            self.paper_format.as_ref().map(|format| format.0),
+            self.paper_width.as_ref().map(|width| width.0),
+            self.paper_height.as_ref().map(|height| height.0),
+        );
+
+        GeneratePdfOptions {
+            landscape: self.landscape,
+            display_header_footer: self.display_header_footer,
+            print_background: self.print_background,
+            scale: self.scale,
+            paper_width,
+            paper_height,
+            margin_top: self.margin_top.as_ref().map(|margin| margin.0.inches()),
+            margin_bottom: self.margin_bottom.as_ref().map(|margin| margin.0.inches()),
+            margin_left: self.margin_left.as_ref().map(|margin| margin.0.inches()),
+            margin_right: self.margin_right.as_ref().map(|margin| margin.0.inches()),
+            page_range: self.page_range.clone(),
+            header_template: self.header_template.clone(),
+            footer_template: self.footer_template.clone(),
+            prefer_css_page_size: self.prefer_css_page_size,
+            generate_tagged_pdf: self
+                .generate_tagged_pdf
+                .or(self.generate_document_outline.filter(|outline| *outline)),
+            generate_document_outline: self.generate_document_outline,
+        }
+    }
+}
+
+pub async fn convert_html(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+    Extension(render_cache): Extension<Arc<RenderCache>>,
+    TypedMultipart(dto): TypedMultipart<ConvertHtmlDto>,
+) -> impl IntoResponse {
+    if let Err(err) = dto.validate() {
+        return validation_error_response(err);
+    }
+
+    let mut page_options = dto.to_page_options();
+    let mut options = dto.to_generate_pdf_options();
+
+    match read_uploaded_templates(&dto.files).await {
+        Ok(uploaded_templates) => uploaded_templates.apply(&mut options),
+        Err(response) => return response,
+    };
+
+    let cache_inputs = [
+        "html".to_string(),
+        format!("{options:?}"),
+        format!("{page_options:?}"),
+    ];
+    let no_cache = dto.no_cache.unwrap_or(false) || dto.console_log.is_some();
+    let cache_key = match lookup_render_cache(
+        &render_cache,
+        no_cache,
+        &dto.files,
+        &cache_inputs,
+        "application/pdf",
+    )
+    .await
+    {
+        Ok(cache_key) => cache_key,
+        Err(response) => return response,
+    };
+
+    let (dir, index_url) = match prepare_html_files(dto.files).await {
+        Ok(prepared) => prepared,
+        Err(response) => return response,
+    };
+    page_options.local_directory = Some(dir.path().to_path_buf());
+
+    let output_result = chromium_service
+        .generate_pdf_from_url(&index_url, &page_options, &options)
+        .await;
+
+    match output_result {
+        Ok(output) => cached_conversion_output_response(
+            &render_cache,
+            cache_key,
+            output,
+            "application/pdf",
+            dto.console_log,
+        ),
+        Err(err) => conversion_error_response(err),
+    }
+}
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use axum::{response::IntoResponse, Extension};
+use axum_typed_multipart::{FieldData, TryFromField, TryFromMultipart, TypedMultipart};
+use tempfile::NamedTempFile;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::services::chromium::{
+    CaptureScreenshotOptions, ChromiumService, ScreenshotClip, ScreenshotFormat,
+};
+
+use super::{
+    conversion_error_response, conversion_output_response, prepare_html_files, read_injected_files,
+    validate_injected_files, validation_error_response,
+};
+
+#[derive(TryFromField, Clone, Copy, PartialEq)]
+#[try_from_field(rename_all = "lowercase")]
+pub enum ScreenshotFormatDto {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl From<ScreenshotFormatDto> for ScreenshotFormat {
+    fn from(value: ScreenshotFormatDto) -> Self {
+        match value {
+            ScreenshotFormatDto::Png => Self::Png,
+            ScreenshotFormatDto::Jpeg => Self::Jpeg,
+            ScreenshotFormatDto::Webp => Self::Webp,
+        }
+    }
+}
+
+fn validate_screenshot_options(
+    format: Option<ScreenshotFormatDto>,
+    quality: Option<i64>,
+    full_page: Option<bool>,
+    clip: [Option<f64>; 4],
+    errors: &mut ValidationErrors,
+) {
+    if quality.is_some() && matches!(format, None | Some(ScreenshotFormatDto::Png)) {
+        let error = ValidationError::new("quality is only supported by the jpeg and webp formats");
+        errors.add("quality", error);
+    }
+
+    let clip_field_count = clip.iter().filter(|value| value.is_some()).count();
+    if clip_field_count != 0 && clip_field_count != clip.len() {
+        let error = ValidationError::new(
+            "clipX, clipY, clipWidth and clipHeight must all be provided to clip the screenshot",
+        );
+        errors.add("clip", error);
+    }
+
+    if clip_field_count != 0 && full_page == Some(true) {
+        let error = ValidationError::new("fullPage cannot be used with a clip");
+        errors.add("fullPage", error);
+    }
+}
+
+fn to_screenshot_clip(clip: [Option<f64>; 4]) -> Option<ScreenshotClip> {
+    match clip {
+        [Some(x), Some(y), Some(width), Some(height)] => Some(ScreenshotClip {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => None,
+    }
+}
+
+page_options_dto! {
+    remote
+    #[derive(TryFromMultipart, Validate)]
+    #[try_from_multipart(rename_all = "camelCase")]
+    pub struct ScreenshotUrlDto {
+        #[validate(url)]
+        url: String,
+        format: Option<ScreenshotFormatDto>,
+        #[validate(range(min = 0, max = 100))]
+        quality: Option<i64>,
+        full_page: Option<bool>,
+        #[validate(range(min = 0))]
+        clip_x: Option<f64>,
+        #[validate(range(min = 0))]
+        clip_y: Option<f64>,
+        #[validate(range(min = 1))]
+        clip_width: Option<f64>,
+        #[validate(range(min = 1))]
+        clip_height: Option<f64>,
+
+        files: Vec<FieldData<NamedTempFile>>,
+    }
+}
+
+impl ScreenshotUrlDto {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = match Validate::validate(&self) {
+            Err(errors) => errors,
+            _ => ValidationErrors::new(),
+        };
+
+        validate_screenshot_options(
+            self.format,
+            self.quality,
+            self.full_page,
+            self.clip(),
+            &mut errors,
+        );
+
+        validate_injected_files(&self.files, &mut errors);
+
+        self.validate_page_options(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn clip(&self) -> [Option<f64>; 4] {
+        [self.clip_x, self.clip_y, self.clip_width, self.clip_height]
+    }
+
+    fn to_capture_screenshot_options(&self) -> CaptureScreenshotOptions {
+        CaptureScreenshotOptions {
+            format: self.format.map(Into::into).unwrap_or_default(),
+            quality: self.quality,
+            full_page: self.full_page,
+            clip: to_screenshot_clip(self.clip()),
+        }
+    }
+}
+
+pub async fn screenshot_url(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+    TypedMultipart(dto): TypedMultipart<ScreenshotUrlDto>,
+) -> impl IntoResponse {
+    if let Err(err) = dto.validate() {
+        return validation_error_response(err);
+    }
+
+    let mut page_options = dto.to_page_options();
+    if let Err(response) = read_injected_files(&dto.files, &[], &mut page_options).await {
+        return response;
+    }
+    let options = dto.to_capture_screenshot_options();
+
+    let output_result = chromium_service
+        .capture_screenshot_from_url(&dto.url, &page_options, &options)
+        .await;
+
+    match output_result {
+        Ok(output) => {
+            conversion_output_response(output, options.format.content_type(), dto.console_log)
+        }
+        Err(err) => conversion_error_response(err),
+    }
+}
+
+page_options_dto! {
+    #[derive(TryFromMultipart, Validate)]
+    #[try_from_multipart(rename_all = "camelCase")]
+    pub struct ScreenshotHtmlDto {
+        files: Vec<FieldData<NamedTempFile>>,
+
+        format: Option<ScreenshotFormatDto>,
+        #[validate(range(min = 0, max = 100))]
+        quality: Option<i64>,
+        full_page: Option<bool>,
+        #[validate(range(min = 0))]
+        clip_x: Option<f64>,
+        #[validate(range(min = 0))]
+        clip_y: Option<f64>,
+        #[validate(range(min = 1))]
+        clip_width: Option<f64>,
+        #[validate(range(min = 1))]
+        clip_height: Option<f64>,
+    }
+}
+
+impl ScreenshotHtmlDto {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = match Validate::validate(&self) {
+            Err(errors) => errors,
+            _ => ValidationErrors::new(),
+        };
+
+        if self.files.is_empty() {
+            let error = ValidationError::new("files must not be empty");
+            errors.add("files", error);
+        }
+
+        validate_screenshot_options(
+            self.format,
+            self.quality,
+            self.full_page,
+            self.clip(),
+            &mut errors,
+        );
+
+        self.validate_page_options(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn clip(&self) -> [Option<f64>; 4] {
+        [self.clip_x, self.clip_y, self.clip_width, self.clip_height]
+    }
+
+    fn to_capture_screenshot_options(&self) -> CaptureScreenshotOptions {
+        CaptureScreenshotOptions {
+            format: self.format.map(Into::into).unwrap_or_default(),
+            quality: self.quality,
+            full_page: self.full_page,
+            clip: to_screenshot_clip(self.clip()),
+        }
+    }
+}
+
+pub async fn screenshot_html(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+    TypedMultipart(dto): TypedMultipart<ScreenshotHtmlDto>,
+) -> impl IntoResponse {
+    if let Err(err) = dto.validate() {
+        return validation_error_response(err);
+    }
+
+    let mut page_options = dto.to_page_options();
+    let options = dto.to_capture_screenshot_options();
+
+    let (dir, index_url) = match prepare_html_files(dto.files).await {
+        Ok(prepared) => prepared,
+        Err(response) => return response,
+    };
+    page_options.local_directory = Some(dir.path().to_path_buf());
+
+    let output_result = chromium_service
+        .capture_screenshot_from_url(&index_url, &page_options, &options)
+        .await;
+
+    match output_result {
+        Ok(output) => {
+            conversion_output_response(output, options.format.content_type(), dto.console_log)
+        }
+        Err(err) => conversion_error_response(err),
+    }
+}
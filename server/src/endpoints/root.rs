@@ -1,6 +1,10 @@
-use axum::{routing::get, Json, Router};
+use std::sync::Arc;
+
+use axum::{routing::get, Extension, Json, Router};
 use serde::Serialize;
 
+use crate::services::chromium::{BrowserSource, ChromiumService};
+
 pub fn router() -> Router {
     Router::new().route("/", get(get_info))
 }
@@ -8,8 +12,95 @@ pub fn router() -> Router {
 #[derive(Serialize)]
 struct GetInfoResponse {
     version: &'static str,
+    chromium: ChromiumInfo,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromiumInfo {
+    version: String,
+    /// Settings the instances are launched with, unless they are remote ones.
+    launch: Option<LaunchInfo>,
+    /// Scheme, host and port of the DevTools endpoints of the remote instances.
+    remote_urls: Option<Vec<String>>,
+    pool_size: usize,
+    restarts: usize,
+    queue: QueueInfo,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchInfo {
+    executable: String,
+    args: Vec<String>,
+    user_data_dir: Option<String>,
+    window_size: Option<String>,
+    launch_timeout_ms: u128,
+    sandbox: bool,
 }
 
-async fn get_info() -> Json<GetInfoResponse> {
-    Json(GetInfoResponse { version: "0.0.1" })
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QueueInfo {
+    max_concurrency: usize,
+    max_size: usize,
+    active: usize,
+    waiting: usize,
+    rejected: u64,
+    average_wait_ms: u64,
+}
+
+async fn get_info(
+    Extension(chromium_service): Extension<Arc<ChromiumService>>,
+) -> Json<GetInfoResponse> {
+    let queue_stats = chromium_service.queue_stats();
+
+    let (launch, remote_urls) = match chromium_service.browser_source() {
+        BrowserSource::Launch(launch_settings) => {
+            let launch = LaunchInfo {
+                executable: launch_settings.executable.display().to_string(),
+                args: launch_settings.args.clone(),
+                user_data_dir: launch_settings
+                    .user_data_dir
+                    .as_ref()
+                    .map(|dir| dir.display().to_string()),
+                window_size: launch_settings
+                    .window_size
+                    .map(|size| format!("{}x{}", size.width, size.height)),
+                launch_timeout_ms: launch_settings.launch_timeout.as_millis(),
+                sandbox: launch_settings.sandbox,
+            };
+
+            (Some(launch), None)
+        }
+        BrowserSource::Remote(urls) => {
+            // The path of WebSocket urls contains the id of the browser, which gives control over
+            // it to anyone reaching its endpoint.
+            let urls = urls
+                .iter()
+                .map(|url| url.origin().ascii_serialization())
+                .collect();
+
+            (None, Some(urls))
+        }
+    };
+
+    Json(GetInfoResponse {
+        version: "0.0.1",
+        chromium: ChromiumInfo {
+            version: chromium_service.version().to_string(),
+            launch,
+            remote_urls,
+            pool_size: chromium_service.pool_size(),
+            restarts: chromium_service.restarts(),
+            queue: QueueInfo {
+                max_concurrency: queue_stats.max_concurrency,
+                max_size: queue_stats.max_size,
+                active: queue_stats.active,
+                waiting: queue_stats.waiting,
+                rejected: queue_stats.rejected,
+                average_wait_ms: queue_stats.average_wait_ms,
+            },
+        },
+    })
 }
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use crate::utils::env::parse_env_var;
+
+const DEFAULT_TTL_SECS: u64 = 60 * 60;
+const DEFAULT_MAX_SIZE_BYTES: u64 = 256 * 1024 * 1024;
+
+pub enum CacheBackend {
+    Memory,
+    /// Documents are stored as files of the directory, and kept across restarts.
+    Disk(PathBuf),
+}
+
+pub struct CacheConfig {
+    /// Where the rendered documents are stored. The cache is disabled when not set.
+    pub backend: Option<CacheBackend>,
+    /// How long a rendered document is served from the cache.
+    pub ttl: Duration,
+    /// Maximum total size of the rendered documents, beyond which the least recently used ones
+    /// are evicted.
+    pub max_size: u64,
+}
+
+impl CacheConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let backend = match parse_env_var::<String>("RUSTENBERG_CACHE_BACKEND")?.as_deref() {
+            None | Some("none") => None,
+            Some("memory") => Some(CacheBackend::Memory),
+            Some("disk") => match parse_env_var::<PathBuf>("RUSTENBERG_CACHE_DIR")? {
+                Some(dir) => Some(CacheBackend::Disk(dir)),
+                None => {
+                    return Err(anyhow!(
+                        "RUSTENBERG_CACHE_DIR must be set when the cache backend is disk"
+                    ))
+                }
+            },
+            Some(value) => {
+                return Err(anyhow!(
+                    "invalid value for RUSTENBERG_CACHE_BACKEND: {value}"
+                ))
+            }
+        };
+
+        let ttl = parse_env_var("RUSTENBERG_CACHE_TTL_SECS")?.unwrap_or(DEFAULT_TTL_SECS);
+        let max_size =
+            parse_env_var("RUSTENBERG_CACHE_MAX_SIZE_BYTES")?.unwrap_or(DEFAULT_MAX_SIZE_BYTES);
+
+        Ok(Self {
+            backend,
+            ttl: Duration::from_secs(ttl),
+            max_size,
+        })
+    }
+}
@@ -0,0 +1,527 @@
+mod config;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::chromium::{ConversionData, ConversionStream};
+
+pub use config::{CacheBackend, CacheConfig};
+
+/// Hash of everything a rendered document depends on, as lowercase hexadecimal.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    fn from_filename(filename: &str) -> Option<Self> {
+        let is_key = filename.len() == 64
+            && filename
+                .bytes()
+                .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'));
+
+        is_key.then(|| Self(filename.to_string()))
+    }
+}
+
+#[derive(Default)]
+pub struct CacheKeyHasher(Sha256);
+
+impl CacheKeyHasher {
+    /// Adds one of the inputs of the document. Inputs are prefixed with their length, so that
+    /// moving bytes from one input to the next one changes the key.
+    pub fn update(&mut self, input: impl AsRef<[u8]>) {
+        let input = input.as_ref();
+        self.0.update((input.len() as u64).to_le_bytes());
+        self.0.update(input);
+    }
+
+    pub fn finish(self) -> CacheKey {
+        CacheKey(format!("{:x}", self.0.finalize()))
+    }
+}
+
+struct CacheEntry {
+    size: u64,
+    created_at: SystemTime,
+    /// Value of the clock of the index when the entry was last used.
+    last_used: u64,
+    /// Content of the entry, for the memory backend.
+    data: Option<Bytes>,
+}
+
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<CacheKey, CacheEntry>,
+    size: u64,
+    clock: u64,
+}
+
+impl CacheIndex {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn insert(&mut self, key: CacheKey, entry: CacheEntry) {
+        self.size += entry.size;
+        if let Some(previous) = self.entries.insert(key, entry) {
+            self.size -= previous.size;
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size -= entry.size;
+        }
+    }
+
+    /// Removes the expired entries, then the least recently used ones until the entries fit in
+    /// the maximum size. Returns the keys of the removed entries.
+    fn evict(&mut self, ttl: Duration, max_size: u64) -> Vec<CacheKey> {
+        let now = SystemTime::now();
+        let mut evicted: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                now.duration_since(entry.created_at)
+                    .is_ok_and(|age| age >= ttl)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &evicted {
+            self.remove(key);
+        }
+
+        if self.size > max_size {
+            let mut entries: Vec<_> = self
+                .entries
+                .iter()
+                .map(|(key, entry)| (entry.last_used, key.clone()))
+                .collect();
+            entries.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+            for (_, key) in entries {
+                if self.size <= max_size {
+                    break;
+                }
+
+                self.remove(&key);
+                evicted.push(key);
+            }
+        }
+
+        evicted
+    }
+}
+
+/// Cache of rendered documents, keyed by a hash of the inputs they were rendered from. Documents
+/// are served from the cache until their time to live expires, and the least recently used ones
+/// are evicted once the cache is full.
+pub struct RenderCache {
+    backend: Option<CacheBackend>,
+    ttl: Duration,
+    max_size: u64,
+    index: Mutex<CacheIndex>,
+}
+
+impl RenderCache {
+    pub async fn new(config: CacheConfig) -> anyhow::Result<Self> {
+        let cache = Self {
+            backend: config.backend,
+            ttl: config.ttl,
+            max_size: config.max_size,
+            index: Mutex::default(),
+        };
+
+        if let Some(CacheBackend::Disk(dir)) = &cache.backend {
+            tokio::fs::create_dir_all(dir).await?;
+            cache.load_directory(dir).await?;
+        }
+
+        Ok(cache)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Indexes the documents stored in the directory by a previous run. Documents which were
+    /// still being written are removed.
+    async fn load_directory(&self, dir: &Path) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+
+        let mut read_dir = tokio::fs::read_dir(dir).await?;
+        while let Some(dir_entry) = read_dir.next_entry().await? {
+            let filename = dir_entry.file_name();
+            let Some(filename) = filename.to_str() else {
+                continue;
+            };
+
+            if filename.ends_with(".tmp") {
+                tokio::fs::remove_file(dir_entry.path()).await?;
+                continue;
+            }
+
+            let Some(key) = CacheKey::from_filename(filename) else {
+                continue;
+            };
+            let metadata = dir_entry.metadata().await?;
+            if metadata.is_file() {
+                entries.push((key, metadata.len(), metadata.modified()?));
+            }
+        }
+
+        let evicted = {
+            let mut index = self.index.lock().unwrap();
+            for (key, size, created_at) in entries {
+                let last_used = index.tick();
+                let entry = CacheEntry {
+                    size,
+                    created_at,
+                    last_used,
+                    data: None,
+                };
+                index.insert(key, entry);
+            }
+
+            index.evict(self.ttl, self.max_size)
+        };
+        self.remove_files(evicted).await;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &CacheKey) -> Option<Bytes> {
+        let backend = self.backend.as_ref()?;
+
+        let (evicted, data) = {
+            let mut index = self.index.lock().unwrap();
+            let evicted = index.evict(self.ttl, self.max_size);
+
+            let last_used = index.tick();
+            let data = index.entries.get_mut(key).map(|entry| {
+                entry.last_used = last_used;
+                entry.data.clone()
+            });
+
+            (evicted, data)
+        };
+        self.remove_files(evicted).await;
+
+        let data = data?;
+        match backend {
+            CacheBackend::Memory => data,
+            CacheBackend::Disk(dir) => match tokio::fs::read(dir.join(&key.0)).await {
+                Ok(data) => Some(data.into()),
+                Err(err) => {
+                    tracing::warn!("could not read cached document: {err}");
+                    self.index.lock().unwrap().remove(key);
+                    None
+                }
+            },
+        }
+    }
+
+    async fn insert(&self, key: CacheKey, data: Bytes) -> anyhow::Result<()> {
+        let Some(backend) = &self.backend else {
+            return Ok(());
+        };
+
+        let size = data.len() as u64;
+        if size > self.max_size {
+            return Ok(());
+        }
+
+        let data = match backend {
+            CacheBackend::Memory => Some(data),
+            CacheBackend::Disk(dir) => {
+                // Documents are written to a temporary file first, so that a document being
+                // written is never read.
+                let temp_path = dir.join(format!("{}.{}.tmp", key.0, Uuid::new_v4()));
+                tokio::fs::write(&temp_path, &data).await?;
+                tokio::fs::rename(&temp_path, dir.join(&key.0)).await?;
+                None
+            }
+        };
+
+        let evicted = {
+            let mut index = self.index.lock().unwrap();
+            let last_used = index.tick();
+            let entry = CacheEntry {
+                size,
+                created_at: SystemTime::now(),
+                last_used,
+                data,
+            };
+            index.insert(key, entry);
+
+            index.evict(self.ttl, self.max_size)
+        };
+        self.remove_files(evicted).await;
+
+        Ok(())
+    }
+
+    async fn remove_files(&self, keys: Vec<CacheKey>) {
+        let Some(CacheBackend::Disk(dir)) = &self.backend else {
+            return;
+        };
+
+        for key in keys {
+            if let Err(err) = tokio::fs::remove_file(dir.join(&key.0)).await {
+                tracing::warn!("could not remove cached document: {err}");
+            }
+        }
+    }
+
+    /// Stores the document under the key once it is fully rendered, while passing it through.
+    /// Documents whose rendering fails, or whose stream is dropped before its end, such as when
+    /// the client disconnects, are not stored.
+    pub fn store(self: &Arc<Self>, key: CacheKey, data: ConversionData) -> ConversionData {
+        match data {
+            ConversionData::Buffered(data) => {
+                self.spawn_insert(key, Bytes::copy_from_slice(&data));
+                ConversionData::Buffered(data)
+            }
+            ConversionData::Streamed(stream) => ConversionData::Streamed(self.tee(key, stream)),
+        }
+    }
+
+    fn tee(self: &Arc<Self>, key: CacheKey, stream: ConversionStream) -> ConversionStream {
+        let state = (stream, Some(BytesMut::new()), self.clone(), key);
+
+        stream::unfold(state, |(mut stream, mut buffer, cache, key)| async move {
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    if let Some(data) = &mut buffer {
+                        if (data.len() + chunk.len()) as u64 > cache.max_size {
+                            buffer = None;
+                        } else {
+                            data.extend_from_slice(&chunk);
+                        }
+                    }
+
+                    Some((Ok(chunk), (stream, buffer, cache, key)))
+                }
+                Some(Err(err)) => Some((Err(err), (stream, None, cache, key))),
+                None => {
+                    if let Some(data) = buffer {
+                        cache.spawn_insert(key, data.freeze());
+                    }
+
+                    None
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn spawn_insert(self: &Arc<Self>, key: CacheKey, data: Bytes) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = cache.insert(key, data).await {
+                tracing::warn!("could not cache rendered document: {err}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::anyhow;
+
+    fn key(digit: char) -> CacheKey {
+        CacheKey(digit.to_string().repeat(64))
+    }
+
+    fn entry(size: u64, created_at: SystemTime, last_used: u64) -> CacheEntry {
+        CacheEntry {
+            size,
+            created_at,
+            last_used,
+            data: None,
+        }
+    }
+
+    fn memory_cache(max_size: u64) -> Arc<RenderCache> {
+        Arc::new(RenderCache {
+            backend: Some(CacheBackend::Memory),
+            ttl: Duration::from_secs(60),
+            max_size,
+            index: Mutex::default(),
+        })
+    }
+
+    fn chunks(chunks: &[&'static [u8]]) -> ConversionStream {
+        let chunks: Vec<_> = chunks.iter().map(|chunk| Ok(Bytes::from(*chunk))).collect();
+        stream::iter(chunks).boxed()
+    }
+
+    /// Lets the tasks spawned to insert the documents run.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    #[test]
+    fn evicts_expired_entries() {
+        let mut index = CacheIndex::default();
+        let now = SystemTime::now();
+        index.insert(key('1'), entry(10, now - Duration::from_secs(120), 1));
+        index.insert(key('2'), entry(10, now, 2));
+
+        let evicted = index.evict(Duration::from_secs(60), 100);
+
+        assert!(evicted == vec![key('1')]);
+        assert!(!index.entries.contains_key(&key('1')));
+        assert!(index.entries.contains_key(&key('2')));
+        assert_eq!(index.size, 10);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries_until_they_fit() {
+        let mut index = CacheIndex::default();
+        let now = SystemTime::now();
+        index.insert(key('1'), entry(10, now, 3));
+        index.insert(key('2'), entry(10, now, 1));
+        index.insert(key('3'), entry(10, now, 2));
+        index.insert(key('4'), entry(10, now, 4));
+
+        let evicted = index.evict(Duration::from_secs(60), 20);
+
+        assert!(evicted == vec![key('2'), key('3')]);
+        assert!(index.entries.contains_key(&key('1')));
+        assert!(index.entries.contains_key(&key('4')));
+        assert_eq!(index.size, 20);
+    }
+
+    #[test]
+    fn replacing_an_entry_only_counts_the_new_size() {
+        let mut index = CacheIndex::default();
+        let now = SystemTime::now();
+        index.insert(key('1'), entry(10, now, 1));
+        index.insert(key('1'), entry(4, now, 2));
+        assert_eq!(index.size, 4);
+
+        index.remove(&key('1'));
+        assert_eq!(index.size, 0);
+    }
+
+    #[test]
+    fn hashes_inputs_with_their_length() {
+        let hash = |inputs: &[&str]| {
+            let mut hasher = CacheKeyHasher::default();
+            for input in inputs {
+                hasher.update(input);
+            }
+            hasher.finish()
+        };
+
+        assert!(hash(&["ab", "c"]) != hash(&["a", "bc"]));
+        assert!(hash(&["abc"]) != hash(&["abc", ""]));
+        assert!(hash(&["ab", "c"]) == hash(&["ab", "c"]));
+    }
+
+    #[test]
+    fn parses_keys_from_filenames() {
+        let mut hasher = CacheKeyHasher::default();
+        hasher.update("document");
+        let key = hasher.finish();
+        assert!(CacheKey::from_filename(&key.0) == Some(key.clone()));
+
+        let temp_filename = format!("{}.{}.tmp", key.0, Uuid::new_v4());
+        assert!(CacheKey::from_filename(&temp_filename).is_none());
+        assert!(CacheKey::from_filename(&key.0[1..]).is_none());
+        assert!(CacheKey::from_filename(&key.0.to_uppercase()).is_none());
+        assert!(CacheKey::from_filename(&"g".repeat(64)).is_none());
+    }
+
+    #[tokio::test]
+    async fn loading_a_directory_removes_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_filename = format!("{}.{}.tmp", key('a').0, Uuid::new_v4());
+        std::fs::write(dir.path().join(&key('a').0), b"document").unwrap();
+        std::fs::write(dir.path().join(&temp_filename), b"docu").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"notes").unwrap();
+
+        let cache = RenderCache::new(CacheConfig {
+            backend: Some(CacheBackend::Disk(dir.path().to_path_buf())),
+            ttl: Duration::from_secs(60),
+            max_size: 1024,
+        })
+        .await
+        .unwrap();
+
+        assert!(!dir.path().join(&temp_filename).exists());
+        assert!(dir.path().join("notes.txt").exists());
+        assert_eq!(cache.index.lock().unwrap().size, 8);
+        assert_eq!(
+            cache.get(&key('a')).await.as_deref(),
+            Some(&b"document"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn stores_fully_read_streams() {
+        let cache = memory_cache(8);
+
+        let data: Vec<_> = cache
+            .tee(key('1'), chunks(&[b"abc", b"def"]))
+            .collect()
+            .await;
+        settle().await;
+
+        assert_eq!(data.len(), 2);
+        assert_eq!(cache.get(&key('1')).await.as_deref(), Some(&b"abcdef"[..]));
+    }
+
+    #[tokio::test]
+    async fn does_not_store_streams_larger_than_the_cache() {
+        let cache = memory_cache(8);
+
+        let data: Vec<_> = cache
+            .tee(key('1'), chunks(&[b"abcde", b"fghij"]))
+            .collect()
+            .await;
+        settle().await;
+
+        assert!(data.iter().all(Result::is_ok));
+        assert!(cache.get(&key('1')).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn does_not_store_failed_streams() {
+        let cache = memory_cache(8);
+        let stream = stream::iter(vec![
+            Ok(Bytes::from_static(b"abc")),
+            Err(anyhow!("could not read")),
+            Ok(Bytes::from_static(b"def")),
+        ])
+        .boxed();
+
+        let data: Vec<_> = cache.tee(key('1'), stream).collect().await;
+        settle().await;
+
+        assert!(data[1].is_err());
+        assert!(cache.get(&key('1')).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn does_not_store_dropped_streams() {
+        let cache = memory_cache(8);
+
+        let mut stream = cache.tee(key('1'), chunks(&[b"abc", b"def"]));
+        assert!(stream.next().await.is_some());
+        drop(stream);
+        settle().await;
+
+        assert!(cache.get(&key('1')).await.is_none());
+    }
+}
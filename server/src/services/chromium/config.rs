@@ -0,0 +1,212 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chromiumoxide::detection::{self, DetectionOptions};
+
+use url::Url;
+
+use crate::utils::env::{parse_env_list, parse_env_var};
+
+use super::{UrlPattern, UrlPolicy};
+
+const DEFAULT_POOL_SIZE: usize = 1;
+const DEFAULT_MAX_CONCURRENCY_PER_INSTANCE: usize = 8;
+const DEFAULT_MAX_QUEUE_SIZE: usize = 64;
+const DEFAULT_WARM_CONTEXTS: usize = 0;
+const DEFAULT_LAUNCH_TIMEOUT_MS: u64 = 20_000;
+/// Any port of the loopback interface.
+const DEFAULT_PROXY_ADDRESS: &str = "127.0.0.1:0";
+
+pub struct ChromiumConfig {
+    /// Whether the chromium instances are launched by the service or already running.
+    pub browser_source: BrowserSource,
+    /// Number of chromium instances used by the service.
+    pub pool_size: usize,
+    /// Maximum number of conversions running at the same time, across all instances.
+    pub max_concurrency: usize,
+    /// Maximum number of conversions waiting for a slot before new ones are rejected.
+    pub max_queue_size: usize,
+    /// Number of browser contexts created ahead of the conversions. 0 disables the warm pool.
+    pub warm_contexts: usize,
+    /// Restrictions applied to the urls requested by the pages.
+    pub url_policy: UrlPolicy,
+    /// Address the proxy enforcing the url policy listens on.
+    pub proxy_address: SocketAddr,
+    /// Url chromium reaches the proxy at, when it differs from the address it listens on.
+    pub proxy_url: Option<Url>,
+    /// Urls which are not loaded by the pages, unless a conversion opts out of it.
+    pub default_blocklist: Vec<UrlPattern>,
+}
+
+impl ChromiumConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let browser_source = BrowserSource::from_env()?;
+
+        let pool_size = parse_env_var("RUSTENBERG_CHROMIUM_POOL_SIZE")?;
+        let pool_size = match &browser_source {
+            BrowserSource::Launch(_) => pool_size.unwrap_or(DEFAULT_POOL_SIZE),
+            // Each remote instance is one instance of the pool.
+            BrowserSource::Remote(urls) if pool_size.is_none() => urls.len(),
+            BrowserSource::Remote(_) => {
+                return Err(anyhow!(
+                    "RUSTENBERG_CHROMIUM_POOL_SIZE must not be set along with \
+                    RUSTENBERG_CHROMIUM_REMOTE_URLS"
+                ))
+            }
+        };
+        if pool_size == 0 {
+            return Err(anyhow!("RUSTENBERG_CHROMIUM_POOL_SIZE must be at least 1"));
+        }
+
+        let max_concurrency = parse_env_var("RUSTENBERG_CHROMIUM_MAX_CONCURRENCY")?
+            .unwrap_or(pool_size * DEFAULT_MAX_CONCURRENCY_PER_INSTANCE);
+        if max_concurrency == 0 {
+            return Err(anyhow!(
+                "RUSTENBERG_CHROMIUM_MAX_CONCURRENCY must be at least 1"
+            ));
+        }
+
+        let max_queue_size =
+            parse_env_var("RUSTENBERG_CHROMIUM_MAX_QUEUE_SIZE")?.unwrap_or(DEFAULT_MAX_QUEUE_SIZE);
+
+        let warm_contexts =
+            parse_env_var("RUSTENBERG_CHROMIUM_WARM_CONTEXTS")?.unwrap_or(DEFAULT_WARM_CONTEXTS);
+
+        let proxy_address = match parse_env_var("RUSTENBERG_URL_PROXY_ADDRESS")? {
+            Some(proxy_address) => proxy_address,
+            None => DEFAULT_PROXY_ADDRESS.parse()?,
+        };
+
+        Ok(Self {
+            browser_source,
+            pool_size,
+            max_concurrency,
+            max_queue_size,
+            warm_contexts,
+            url_policy: UrlPolicy::from_env()?,
+            proxy_address,
+            proxy_url: parse_env_var("RUSTENBERG_URL_PROXY_URL")?,
+            default_blocklist: read_default_blocklist()?,
+        })
+    }
+}
+
+pub enum BrowserSource {
+    /// Chromium instances launched, and relaunched when they crash, by the service.
+    Launch(LaunchSettings),
+    /// Chromium instances already running, such as in a sidecar container, reached through
+    /// their DevTools endpoint. The service reconnects to them when the connection is lost.
+    Remote(Vec<Url>),
+}
+
+impl BrowserSource {
+    fn from_env() -> anyhow::Result<Self> {
+        let Some(urls) = parse_env_list::<Url>("RUSTENBERG_CHROMIUM_REMOTE_URLS")? else {
+            return Ok(Self::Launch(LaunchSettings::from_env()?));
+        };
+        if urls.is_empty() {
+            return Ok(Self::Launch(LaunchSettings::from_env()?));
+        }
+
+        if let Some(url) = urls
+            .iter()
+            .find(|url| !matches!(url.scheme(), "ws" | "wss" | "http" | "https"))
+        {
+            return Err(anyhow!(
+                "invalid value for RUSTENBERG_CHROMIUM_REMOTE_URLS: {url}: \
+                expected a ws, wss, http or https url"
+            ));
+        }
+
+        Ok(Self::Remote(urls))
+    }
+}
+
+/// Settings the chromium instances are launched with.
+pub struct LaunchSettings {
+    /// Path of the chromium executable. Detected from the `CHROME` environment variable and the
+    /// usual install locations when not configured.
+    pub executable: PathBuf,
+    /// Flags passed to chromium, on top of the default ones.
+    pub args: Vec<String>,
+    /// Directory under which each launch of an instance gets its own user data directory.
+    /// Chromium's default is used when not set.
+    pub user_data_dir: Option<PathBuf>,
+    pub window_size: Option<WindowSize>,
+    /// Maximum time to wait for an instance to be ready.
+    pub launch_timeout: Duration,
+    /// Run chromium with its sandbox, which requires the server to not run as root.
+    pub sandbox: bool,
+}
+
+impl LaunchSettings {
+    fn from_env() -> anyhow::Result<Self> {
+        let executable = match parse_env_var::<PathBuf>("RUSTENBERG_CHROMIUM_EXECUTABLE")? {
+            Some(executable) => executable,
+            None => detection::default_executable(DetectionOptions::default())
+                .map_err(|err| anyhow!("could not find a chromium executable: {err}"))?,
+        };
+
+        // Flags are separated by whitespaces, since some of them contain commas.
+        let args = parse_env_var::<String>("RUSTENBERG_CHROMIUM_ARGS")?
+            .map(|args| args.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+
+        let launch_timeout_ms = parse_env_var("RUSTENBERG_CHROMIUM_LAUNCH_TIMEOUT_MS")?
+            .unwrap_or(DEFAULT_LAUNCH_TIMEOUT_MS);
+
+        Ok(Self {
+            executable,
+            args,
+            user_data_dir: parse_env_var("RUSTENBERG_CHROMIUM_USER_DATA_DIR")?,
+            window_size: parse_env_var("RUSTENBERG_CHROMIUM_WINDOW_SIZE")?,
+            launch_timeout: Duration::from_millis(launch_timeout_ms),
+            sandbox: parse_env_var("RUSTENBERG_CHROMIUM_SANDBOX")?.unwrap_or(false),
+        })
+    }
+}
+
+/// Size of the browser window, parsed from `<width>x<height>`, such as `1920x1080`.
+#[derive(Clone, Copy)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for WindowSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s.split_once('x').and_then(|(width, height)| {
+            Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+        });
+
+        match parsed {
+            Some((width, height)) if width > 0 && height > 0 => Ok(Self { width, height }),
+            _ => Err(anyhow!(
+                "window size `{s}` must be formatted as <width>x<height>"
+            )),
+        }
+    }
+}
+
+/// Reads the url patterns of the blocklist file, one per line. Empty lines and lines starting
+/// with `#` are ignored.
+fn read_default_blocklist() -> anyhow::Result<Vec<UrlPattern>> {
+    let Some(path) = parse_env_var::<String>("RUSTENBERG_DEFAULT_BLOCKLIST_FILE")? else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|err| anyhow!("could not read default blocklist file {path}: {err}"))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| UrlPattern::from(line.to_string()))
+        .collect())
+}
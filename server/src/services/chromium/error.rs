@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use super::{ConsoleMessage, FailedResource};
+
+/// Errors returned by the chromium service that callers are expected to handle.
+#[derive(Debug, thiserror::Error)]
+pub enum ConversionError {
+    #[error("too many conversions are in progress, try again later")]
+    QueueFull { retry_after: Duration },
+    #[error("no chromium instance is available, try again later")]
+    BrowserUnavailable,
+    #[error("timed out waiting for {condition}")]
+    WaitTimeout { condition: String },
+    #[error("page responded with status {status}")]
+    HttpStatus { status: i64 },
+    #[error("page logged {} console error(s)", messages.len())]
+    ConsoleErrors { messages: Vec<ConsoleMessage> },
+    #[error("{} resource(s) failed to load", resources.len())]
+    ResourceLoadingFailed { resources: Vec<FailedResource> },
+    #[error("url {url} is not allowed: {reason}")]
+    UrlNotAllowed { url: String, reason: String },
+    #[error("{} injected script(s) failed", errors.len())]
+    InjectionFailed { errors: Vec<String> },
+    #[error("invalid {option}: {reason}")]
+    InvalidOption {
+        option: &'static str,
+        reason: String,
+    },
+}
@@ -0,0 +1,115 @@
+use chromiumoxide::{
+    cdp::{
+        browser_protocol::page::{AddScriptToEvaluateOnNewDocumentParams, SetBypassCspParams},
+        js_protocol::runtime::{EvaluateParams, ExceptionDetails},
+    },
+    Page,
+};
+
+use super::options::{PageOptions, ScriptTiming};
+
+/// Global variable collecting the errors thrown by the scripts injected at document start, as
+/// they run before the conversion can observe them.
+const DOCUMENT_START_ERRORS_VARIABLE: &str = "__rustenbergInjectedScriptErrors";
+
+fn has_injections(page_options: &PageOptions) -> bool {
+    !page_options.inject_css.is_empty() || !page_options.inject_js.is_empty()
+}
+
+/// Prepares the page for the injections, before navigating. The content security policy of the
+/// page is bypassed, so that it does not prevent the injected styles and scripts from applying.
+pub async fn prepare(page: &Page, page_options: &PageOptions) -> anyhow::Result<()> {
+    if !has_injections(page_options) {
+        return Ok(());
+    }
+
+    page.execute(SetBypassCspParams::new(true)).await?;
+
+    if page_options.inject_js_timing == ScriptTiming::DocumentStart {
+        for script in &page_options.inject_js {
+            // The script is evaluated through `eval`, so that syntax errors are caught as well.
+            let source = format!(
+                "try {{ (0, eval)({script}); }} catch (error) {{ \
+                     (window.{DOCUMENT_START_ERRORS_VARIABLE} ||= []) \
+                         .push(String((error && error.stack) || error)); \
+                 }}",
+                script = serde_json::to_string(script)?,
+            );
+
+            page.execute(AddScriptToEvaluateOnNewDocumentParams::new(source))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Injects the styles, and the scripts that run after load, into the loaded page. Returns the
+/// errors thrown by the injected scripts, including the ones injected at document start.
+pub async fn apply(page: &Page, page_options: &PageOptions) -> anyhow::Result<Vec<String>> {
+    if !has_injections(page_options) {
+        return Ok(Vec::new());
+    }
+
+    let mut errors = Vec::new();
+
+    for css in &page_options.inject_css {
+        let expression = format!(
+            "(() => {{ \
+                 const style = document.createElement('style'); \
+                 style.textContent = {css}; \
+                 (document.head || document.documentElement).appendChild(style); \
+             }})()",
+            css = serde_json::to_string(css)?,
+        );
+
+        if let Some(error) = evaluate(page, expression).await? {
+            errors.push(error);
+        }
+    }
+
+    match page_options.inject_js_timing {
+        ScriptTiming::DocumentStart => {
+            let expression = format!("window.{DOCUMENT_START_ERRORS_VARIABLE} || []");
+            let result = page.execute(evaluate_params(expression)).await?;
+            if let Some(value) = result.result.result.value.clone() {
+                errors.extend(serde_json::from_value::<Vec<String>>(value)?);
+            }
+        }
+        ScriptTiming::Load => {
+            for script in &page_options.inject_js {
+                if let Some(error) = evaluate(page, script.clone()).await? {
+                    errors.push(error);
+                }
+            }
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Evaluates the expression in the page, awaiting the promise it returns if any. Returns the
+/// error thrown by the expression, if it throws.
+async fn evaluate(page: &Page, expression: String) -> anyhow::Result<Option<String>> {
+    let result = page.execute(evaluate_params(expression)).await?;
+    Ok(result
+        .result
+        .exception_details
+        .as_ref()
+        .map(exception_to_string))
+}
+
+fn evaluate_params(expression: String) -> EvaluateParams {
+    let mut params = EvaluateParams::new(expression);
+    params.await_promise = Some(true);
+    params.return_by_value = Some(true);
+    params
+}
+
+fn exception_to_string(details: &ExceptionDetails) -> String {
+    details
+        .exception
+        .as_ref()
+        .and_then(|exception| exception.description.clone())
+        .unwrap_or_else(|| details.text.clone())
+}
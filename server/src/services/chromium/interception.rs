@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        fetch::{
+            ContinueRequestParams, EnableParams, EventRequestPaused, FailRequestParams,
+            HeaderEntry, RequestPattern,
+        },
+        network::{ErrorReason, ResourceType, SetBlockedUrLsParams},
+    },
+    Page,
+};
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+use url::{Origin, Url};
+
+use super::options::{PageOptions, ResourceTypes};
+use super::{UrlPattern, UrlPolicy};
+
+/// Extra http headers that are only sent to a single origin.
+struct ScopedHeaders {
+    origin: Origin,
+    headers: HashMap<String, String>,
+}
+
+/// Request of the main document which was blocked, along with the reason why.
+pub struct BlockedNavigation {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Decides what happens to each request of a page, using the CDP `Fetch` domain.
+pub struct RequestInterceptor {
+    url_policy: Arc<UrlPolicy>,
+    /// Directory of the uploaded files the page is loaded from, with its symbolic links resolved.
+    local_directory: Option<PathBuf>,
+    scoped_headers: Option<ScopedHeaders>,
+    blocked_resource_types: ResourceTypes,
+    blocked_urls: Vec<UrlPattern>,
+    main_frame_id: Option<String>,
+    blocked_navigation: Mutex<Option<BlockedNavigation>>,
+}
+
+impl RequestInterceptor {
+    pub fn new(
+        url: &str,
+        page_options: &PageOptions,
+        url_policy: Arc<UrlPolicy>,
+        default_blocklist: &[UrlPattern],
+    ) -> anyhow::Result<Self> {
+        let mut scoped_headers = None;
+        if page_options.extra_http_headers_origin_only
+            && !page_options.extra_http_headers.is_empty()
+        {
+            scoped_headers = Some(ScopedHeaders {
+                origin: Url::parse(url)?.origin(),
+                headers: page_options.extra_http_headers.clone(),
+            });
+        }
+
+        Ok(Self {
+            url_policy,
+            local_directory: page_options
+                .local_directory
+                .as_ref()
+                .map(std::fs::canonicalize)
+                .transpose()?,
+            scoped_headers,
+            blocked_resource_types: page_options.blocked_resource_types.clone(),
+            blocked_urls: [default_blocklist, &page_options.blocked_urls].concat(),
+            main_frame_id: None,
+            blocked_navigation: Mutex::new(None),
+        })
+    }
+
+    /// Checks that the url may be requested by the page, and returns the reason why it may not
+    /// otherwise.
+    pub async fn check_url(&self, url: &str) -> Result<(), String> {
+        match &self.local_directory {
+            Some(directory) => self.url_policy.check_local(url, directory).await,
+            None => self.url_policy.check(url).await,
+        }
+    }
+
+    /// Enables request interception on the page. Paused requests are handled until the returned
+    /// guard is dropped, or until the page is closed.
+    pub async fn attach(mut self, page: &Page) -> anyhow::Result<InterceptionGuard> {
+        self.main_frame_id = Some(page.target_id().as_ref().to_string());
+        let mut events = page.event_listener::<EventRequestPaused>().await?;
+
+        page.execute(EnableParams {
+            patterns: Some(vec![RequestPattern {
+                url_pattern: Some("*".to_string()),
+                resource_type: None,
+                request_stage: None,
+            }]),
+            handle_auth_requests: None,
+        })
+        .await?;
+
+        // WebSocket handshakes are not paused by the `Fetch` domain, they are blocked altogether
+        // when their scheme is not allowed.
+        let blocked_websocket_urls: Vec<_> = ["ws", "wss"]
+            .into_iter()
+            .filter(|scheme| {
+                !self
+                    .url_policy
+                    .is_scheme_allowed(scheme, self.local_directory.is_some())
+            })
+            .map(|scheme| format!("{scheme}://*"))
+            .collect();
+        if !blocked_websocket_urls.is_empty() {
+            page.execute(SetBlockedUrLsParams::new(blocked_websocket_urls))
+                .await?;
+        }
+
+        let interceptor = Arc::new(self);
+        let page = page.clone();
+
+        let handle_interceptor = interceptor.clone();
+        let handle = tokio::spawn(async move {
+            let interceptor = handle_interceptor;
+            while let Some(event) = events.next().await {
+                let interceptor = interceptor.clone();
+                let page = page.clone();
+
+                // Requests are handled concurrently, so that a slow response to a command does
+                // not hold back the other requests of the page.
+                tokio::spawn(async move {
+                    if interceptor.is_blocked(&event) {
+                        let params = FailRequestParams::new(
+                            event.request_id.clone(),
+                            ErrorReason::BlockedByClient,
+                        );
+                        if let Err(err) = page.execute(params).await {
+                            tracing::debug!("could not block intercepted request: {err}");
+                        }
+
+                        return;
+                    }
+
+                    let result = match interceptor.check_url(&event.request.url).await {
+                        Ok(()) => page
+                            .execute(interceptor.continue_request_params(&event))
+                            .await
+                            .map(|_| ()),
+                        Err(reason) => {
+                            tracing::debug!("blocked request to {}: {reason}", event.request.url);
+                            interceptor.record_blocked_request(&event, reason);
+
+                            page.execute(FailRequestParams::new(
+                                event.request_id.clone(),
+                                ErrorReason::AccessDenied,
+                            ))
+                            .await
+                            .map(|_| ())
+                        }
+                    };
+
+                    if let Err(err) = result {
+                        tracing::debug!("could not resolve intercepted request: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(InterceptionGuard {
+            handle,
+            interceptor,
+        })
+    }
+
+    /// Whether the request is blocked by the resource types or urls to block. The main document is
+    /// never blocked by its resource type.
+    fn is_blocked(&self, event: &EventRequestPaused) -> bool {
+        let is_blocked_type = !self.is_main_document(event)
+            && self.blocked_resource_types.contains(&event.resource_type);
+
+        is_blocked_type
+            || self
+                .blocked_urls
+                .iter()
+                .any(|pattern| pattern.matches(&event.request.url))
+    }
+
+    fn is_main_document(&self, event: &EventRequestPaused) -> bool {
+        event.resource_type == ResourceType::Document
+            && self.main_frame_id.as_deref() == Some(event.frame_id.as_ref())
+    }
+
+    fn record_blocked_request(&self, event: &EventRequestPaused, reason: String) {
+        if self.is_main_document(event) {
+            *self.blocked_navigation.lock().unwrap() = Some(BlockedNavigation {
+                url: event.request.url.clone(),
+                reason,
+            });
+        }
+    }
+
+    fn continue_request_params(&self, event: &EventRequestPaused) -> ContinueRequestParams {
+        let mut params = ContinueRequestParams::new(event.request_id.clone());
+
+        if let Some(scoped_headers) = &self.scoped_headers {
+            let is_same_origin = Url::parse(&event.request.url)
+                .is_ok_and(|url| url.origin() == scoped_headers.origin);
+
+            if is_same_origin {
+                params.headers = Some(merge_headers(event, &scoped_headers.headers));
+            }
+        }
+
+        params
+    }
+}
+
+pub struct InterceptionGuard {
+    handle: JoinHandle<()>,
+    interceptor: Arc<RequestInterceptor>,
+}
+
+impl InterceptionGuard {
+    /// Main document request which was blocked, such as a redirect to a denied url.
+    pub fn take_blocked_navigation(&self) -> Option<BlockedNavigation> {
+        self.interceptor.blocked_navigation.lock().unwrap().take()
+    }
+}
+
+impl Drop for InterceptionGuard {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Headers of the request, with the extra headers added. Extra headers replace the request's
+/// headers that have the same name.
+fn merge_headers(
+    event: &EventRequestPaused,
+    extra_headers: &HashMap<String, String>,
+) -> Vec<HeaderEntry> {
+    let mut headers = Vec::new();
+
+    if let Some(request_headers) = event.request.headers.inner().as_object() {
+        for (name, value) in request_headers {
+            let is_overridden = extra_headers
+                .keys()
+                .any(|extra_name| extra_name.eq_ignore_ascii_case(name));
+
+            if let (false, Some(value)) = (is_overridden, value.as_str()) {
+                headers.push(HeaderEntry::new(name.clone(), value));
+            }
+        }
+    }
+
+    headers.extend(
+        extra_headers
+            .iter()
+            .map(|(name, value)| HeaderEntry::new(name.clone(), value.clone())),
+    );
+
+    headers
+}
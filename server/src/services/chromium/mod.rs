@@ -0,0 +1,514 @@
+mod config;
+mod error;
+mod injection;
+mod interception;
+mod monitor;
+mod options;
+mod pool;
+mod proxy;
+mod queue;
+mod session;
+mod url_pattern;
+mod url_policy;
+mod warm;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        browser::GetVersionParams,
+        emulation::{
+            self, SetDeviceMetricsOverrideParams, SetEmulatedMediaParams, SetLocaleOverrideParams,
+            SetTimezoneOverrideParams, SetUserAgentOverrideParams,
+        },
+        network::{Headers, SetCookiesParams, SetExtraHttpHeadersParams},
+        page::{CaptureScreenshotParams, PrintToPdfTransferMode, Viewport},
+    },
+    Page,
+};
+use futures::StreamExt;
+use tokio::time::Instant;
+
+use crate::utils::chromium_pages::{
+    wait_for_expression, wait_for_selector, wait_until_page_fully_loaded_with_bounds,
+};
+
+pub use config::{BrowserSource, ChromiumConfig};
+pub use error::ConversionError;
+use interception::{InterceptionGuard, RequestInterceptor};
+use monitor::PageMonitor;
+pub use monitor::{ConsoleMessage, FailedResource};
+use options::PrintToPdfCommand;
+pub use options::{
+    CaptureScreenshotOptions, Cookie, DeviceMetrics, DevicePreset, GeneratePdfOptions,
+    HttpStatusCodes, Length, MediaFeature, MediaType, PageOptions, PaperFormat, ResourceTypes,
+    ScreenshotClip, ScreenshotFormat, ScriptTiming,
+};
+use pool::BrowserPool;
+use proxy::UrlPolicyProxy;
+use queue::ConversionQueue;
+pub use queue::ConversionQueueStats;
+pub use session::ConversionStream;
+use session::{BrowserContextGuard, PageSession};
+pub use url_pattern::UrlPattern;
+pub use url_policy::UrlPolicy;
+use warm::WarmContextPool;
+
+const DEFAULT_MIN_PAGE_LOAD_TIME_MS: u64 = 0;
+const DEFAULT_MAX_PAGE_LOAD_TIME_MS: u64 = 5000;
+
+/// Output of a conversion, along with the console messages logged by the page while converting.
+pub struct ConversionOutput {
+    pub data: ConversionData,
+    pub console_messages: Vec<ConsoleMessage>,
+}
+
+pub enum ConversionData {
+    Buffered(Vec<u8>),
+    /// Output read from chromium as it is sent, which keeps the page open until it is consumed or
+    /// dropped.
+    Streamed(ConversionStream),
+}
+
+impl ConversionData {
+    pub fn into_stream(self) -> ConversionStream {
+        match self {
+            Self::Buffered(data) => futures::stream::once(async { Ok(data.into()) }).boxed(),
+            Self::Streamed(stream) => stream,
+        }
+    }
+}
+
+pub struct ChromiumService {
+    pool: Arc<BrowserPool>,
+    /// Product name and version of chromium, as detected on launch.
+    version: String,
+    warm_contexts: Arc<WarmContextPool>,
+    queue: ConversionQueue,
+    url_policy: Arc<UrlPolicy>,
+    _proxy: UrlPolicyProxy,
+    default_blocklist: Vec<UrlPattern>,
+}
+
+impl ChromiumService {
+    pub async fn new(config: ChromiumConfig) -> anyhow::Result<Self> {
+        let url_policy = Arc::new(config.url_policy);
+        let proxy = UrlPolicyProxy::start(
+            config.proxy_address,
+            config
+                .proxy_url
+                .map(|url| url.origin().ascii_serialization()),
+            url_policy.clone(),
+        )
+        .await?;
+        tracing::info!("url policy proxy reachable at {}", proxy.url());
+
+        let pool =
+            BrowserPool::launch(config.pool_size, config.browser_source, proxy.url()).await?;
+        let pool = Arc::new(pool);
+        let version = pool.version().await?;
+        tracing::info!("started {} chromium instance(s): {version}", pool.size());
+
+        let warm_contexts = WarmContextPool::new(pool.clone(), config.warm_contexts);
+        let queue = ConversionQueue::new(config.max_concurrency, config.max_queue_size);
+
+        Ok(Self {
+            pool,
+            version,
+            warm_contexts,
+            queue,
+            url_policy,
+            _proxy: proxy,
+            default_blocklist: config.default_blocklist,
+        })
+    }
+
+    pub fn pool_size(&self) -> usize {
+        self.pool.size()
+    }
+
+    pub fn restarts(&self) -> usize {
+        self.pool.restarts()
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn browser_source(&self) -> &BrowserSource {
+        self.pool.source()
+    }
+
+    pub fn queue_stats(&self) -> ConversionQueueStats {
+        self.queue.stats()
+    }
+
+    pub async fn generate_pdf_from_url(
+        &self,
+        url: &str,
+        page_options: &PageOptions,
+        options: &GeneratePdfOptions,
+    ) -> anyhow::Result<ConversionOutput> {
+        let mut command = PrintToPdfCommand::from(options);
+        command.params.transfer_mode = Some(PrintToPdfTransferMode::ReturnAsStream);
+
+        let (session, console_messages) = self.load_page(url, page_options).await?;
+
+        let handle = match session.page.execute(command).await {
+            Ok(response) => response.result.stream,
+            Err(err) => {
+                close_session(session).await;
+                return Err(err.into());
+            }
+        };
+
+        let Some(handle) = handle else {
+            close_session(session).await;
+            return Err(anyhow!("chromium did not return a pdf stream"));
+        };
+
+        Ok(ConversionOutput {
+            data: ConversionData::Streamed(session.into_stream(handle)),
+            console_messages,
+        })
+    }
+
+    pub async fn capture_screenshot_from_url(
+        &self,
+        url: &str,
+        page_options: &PageOptions,
+        options: &CaptureScreenshotOptions,
+    ) -> anyhow::Result<ConversionOutput> {
+        let (session, console_messages) = self.load_page(url, page_options).await?;
+
+        let screenshot = match capture_screenshot(&session.page, options).await {
+            Ok(screenshot) => screenshot,
+            Err(err) => {
+                close_session(session).await;
+                return Err(err);
+            }
+        };
+        session.close().await?;
+
+        Ok(ConversionOutput {
+            data: ConversionData::Buffered(screenshot),
+            console_messages,
+        })
+    }
+
+    /// Loads the url in a new page, isolated in its own browser context, and returns the page once
+    /// it is fully loaded, along with the console messages it logged. The browser context is
+    /// disposed once the session is closed or dropped.
+    async fn load_page(
+        &self,
+        url: &str,
+        page_options: &PageOptions,
+    ) -> anyhow::Result<(PageSession, Vec<ConsoleMessage>)> {
+        let default_blocklist = match page_options.skip_default_blocklist {
+            true => &[][..],
+            false => &self.default_blocklist,
+        };
+        let interceptor = RequestInterceptor::new(
+            url,
+            page_options,
+            self.url_policy.clone(),
+            default_blocklist,
+        )?;
+        if let Err(reason) = interceptor.check_url(url).await {
+            return Err(ConversionError::UrlNotAllowed {
+                url: url.to_string(),
+                reason,
+            }
+            .into());
+        }
+
+        let permit = self.queue.acquire().await?;
+        let (browser_context, page) = match self.warm_contexts.take() {
+            Some(warm_context) => warm_context,
+            None => BrowserContextGuard::create(self.pool.acquire()?).await?,
+        };
+
+        let result = async {
+            let interception = prepare_page(&page, url, page_options, interceptor).await?;
+            let monitor = PageMonitor::attach(&page).await?;
+
+            let max_page_load_time = Duration::from_millis(
+                page_options
+                    .max_page_load_time_ms
+                    .unwrap_or(DEFAULT_MAX_PAGE_LOAD_TIME_MS),
+            );
+            let page_load_deadline = Instant::now() + max_page_load_time;
+
+            let page_load_handle = tokio::spawn(wait_until_page_fully_loaded_with_bounds(
+                page.clone(),
+                Duration::from_millis(
+                    page_options
+                        .min_page_load_time_ms
+                        .unwrap_or(DEFAULT_MIN_PAGE_LOAD_TIME_MS),
+                ),
+                max_page_load_time,
+            ));
+
+            if let Err(err) = page.goto(url).await {
+                page_load_handle.abort();
+                check_blocked_navigation(&interception)?;
+
+                return Err(anyhow!(err));
+            }
+
+            page_load_handle.await??;
+            // The page may have navigated to a denied url after it was loaded, such as through a
+            // client-side redirect, in which case the error page would otherwise be rendered.
+            check_blocked_navigation(&interception)?;
+
+            if let Some(status) = monitor.main_document_status() {
+                if page_options.fail_on_http_status_codes.contains(status) {
+                    return Err(ConversionError::HttpStatus { status }.into());
+                }
+            }
+
+            let injection_errors = injection::apply(&page, page_options).await?;
+            if !injection_errors.is_empty() {
+                return Err(ConversionError::InjectionFailed {
+                    errors: injection_errors,
+                }
+                .into());
+            }
+
+            if let Some(selector) = &page_options.wait_for_selector {
+                let found = wait_for_selector(&page, selector, page_load_deadline).await;
+                check_blocked_navigation(&interception)?;
+                if !found {
+                    return Err(ConversionError::WaitTimeout {
+                        condition: format!("selector `{selector}`"),
+                    }
+                    .into());
+                }
+            }
+
+            if let Some(expression) = &page_options.wait_for_expression {
+                let truthy = wait_for_expression(&page, expression, page_load_deadline).await;
+                check_blocked_navigation(&interception)?;
+                if !truthy {
+                    return Err(ConversionError::WaitTimeout {
+                        condition: format!("expression `{expression}`"),
+                    }
+                    .into());
+                }
+            }
+
+            if page_options.fail_on_resource_loading_failed {
+                let failed_resources: Vec<_> = monitor
+                    .failed_resources()
+                    .into_iter()
+                    .filter(|resource| {
+                        !page_options
+                            .ignored_resource_loading_failures
+                            .iter()
+                            .any(|pattern| pattern.matches(&resource.url))
+                    })
+                    .collect();
+
+                if !failed_resources.is_empty() {
+                    return Err(ConversionError::ResourceLoadingFailed {
+                        resources: failed_resources,
+                    }
+                    .into());
+                }
+            }
+
+            let console_messages = monitor.console_messages();
+            if page_options.fail_on_console_errors {
+                let errors: Vec<_> = console_messages
+                    .iter()
+                    .filter(|message| message.is_error())
+                    .cloned()
+                    .collect();
+
+                if !errors.is_empty() {
+                    return Err(ConversionError::ConsoleErrors { messages: errors }.into());
+                }
+            }
+
+            Ok((interception, monitor, console_messages))
+        }
+        .await;
+
+        match result {
+            Ok((interception, monitor, console_messages)) => {
+                let session = PageSession {
+                    page,
+                    _interception: interception,
+                    _monitor: monitor,
+                    browser_context,
+                    _permit: permit,
+                };
+
+                Ok((session, console_messages))
+            }
+            Err(err) => {
+                if let Err(dispose_err) = browser_context.dispose().await {
+                    tracing::warn!("could not dispose browser context: {dispose_err}");
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Fails with the main document request which was blocked, if any.
+fn check_blocked_navigation(interception: &InterceptionGuard) -> Result<(), ConversionError> {
+    match interception.take_blocked_navigation() {
+        Some(blocked) => Err(ConversionError::UrlNotAllowed {
+            url: blocked.url,
+            reason: blocked.reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Closes the session of a failed conversion. Failing to close it is only logged, so that the
+/// error of the conversion is the one returned.
+async fn close_session(session: PageSession) {
+    if let Err(err) = session.close().await {
+        tracing::warn!("could not dispose browser context: {err}");
+    }
+}
+
+async fn capture_screenshot(
+    page: &Page,
+    options: &CaptureScreenshotOptions,
+) -> anyhow::Result<Vec<u8>> {
+    let mut clip = options.clip.as_ref().map(|clip| Viewport {
+        x: clip.x,
+        y: clip.y,
+        width: clip.width,
+        height: clip.height,
+        scale: 1.,
+    });
+
+    if options.full_page.unwrap_or(false) {
+        let metrics = page.layout_metrics().await?;
+        clip = Some(Viewport {
+            x: 0.,
+            y: 0.,
+            width: metrics.css_content_size.width,
+            height: metrics.css_content_size.height,
+            scale: 1.,
+        });
+    }
+
+    let params = CaptureScreenshotParams {
+        format: Some(options.format.into()),
+        quality: options.quality,
+        capture_beyond_viewport: Some(clip.is_some()),
+        clip,
+        from_surface: None,
+    };
+
+    let screenshot = page.execute(params).await?;
+    Ok(BASE64.decode(&screenshot.result.data)?)
+}
+
+/// Applies the page options that must be set before navigating to the url, and enables request
+/// interception.
+async fn prepare_page(
+    page: &Page,
+    url: &str,
+    page_options: &PageOptions,
+    interceptor: RequestInterceptor,
+) -> anyhow::Result<InterceptionGuard> {
+    if !page_options.cookies.is_empty() {
+        let cookies = page_options
+            .cookies
+            .iter()
+            .map(|cookie| cookie.to_cookie_param(url))
+            .collect();
+
+        page.execute(SetCookiesParams::new(cookies)).await?;
+    }
+
+    let has_extra_http_headers = !page_options.extra_http_headers.is_empty();
+    if has_extra_http_headers && !page_options.extra_http_headers_origin_only {
+        let headers = Headers::new(serde_json::to_value(&page_options.extra_http_headers)?);
+        page.execute(SetExtraHttpHeadersParams::new(headers))
+            .await?;
+    }
+
+    if let Some(metrics) = &page_options.device_metrics {
+        page.execute(SetDeviceMetricsOverrideParams::new(
+            metrics.width,
+            metrics.height,
+            metrics.device_scale_factor,
+            metrics.mobile,
+        ))
+        .await?;
+    }
+
+    if let Some(timezone) = &page_options.timezone {
+        page.execute(SetTimezoneOverrideParams::new(timezone))
+            .await
+            .map_err(|err| ConversionError::InvalidOption {
+                option: "timezone",
+                reason: err.to_string(),
+            })?;
+    }
+
+    if let Some(locale) = &page_options.locale {
+        page.execute(SetLocaleOverrideParams {
+            locale: Some(locale.clone()),
+        })
+        .await
+        .map_err(|err| ConversionError::InvalidOption {
+            option: "locale",
+            reason: err.to_string(),
+        })?;
+    }
+
+    let accept_language = page_options
+        .accept_language
+        .as_ref()
+        .or(page_options.locale.as_ref());
+    if page_options.user_agent.is_some() || accept_language.is_some() {
+        // The user agent must always be overridden along with the accept language, so the
+        // browser's own one is kept when none is given.
+        let user_agent = match &page_options.user_agent {
+            Some(user_agent) => user_agent.clone(),
+            None => page
+                .execute(GetVersionParams::default())
+                .await?
+                .result
+                .user_agent
+                .clone(),
+        };
+
+        let mut params = SetUserAgentOverrideParams::new(user_agent);
+        params.accept_language = accept_language.cloned();
+        page.execute(params).await?;
+    }
+
+    if page_options.emulated_media_type.is_some()
+        || !page_options.emulated_media_features.is_empty()
+    {
+        let features = page_options
+            .emulated_media_features
+            .iter()
+            .map(|feature| emulation::MediaFeature::new(&feature.name, &feature.value))
+            .collect();
+
+        page.execute(SetEmulatedMediaParams {
+            media: page_options
+                .emulated_media_type
+                .map(|media_type| media_type.as_str().to_string()),
+            features: Some(features),
+        })
+        .await?;
+    }
+
+    injection::prepare(page, page_options).await?;
+
+    interceptor.attach(page).await
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::{
+    cdp::{
+        browser_protocol::network::{
+            EventLoadingFailed, EventRequestWillBeSent, EventResponseReceived, ResourceType,
+        },
+        js_protocol::runtime::{
+            ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown, RemoteObject,
+        },
+    },
+    Page,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+/// Error of the requests failed by the interception with the `BlockedByClient` reason.
+const BLOCKED_BY_CLIENT_ERROR: &str = "net::ERR_BLOCKED_BY_CLIENT";
+
+/// Message logged in the console of a page, or exception thrown by one of its scripts.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConsoleMessage {
+    /// Type of the console call, such as `log` or `error`, or `exception` for uncaught exceptions.
+    pub level: String,
+    pub text: String,
+}
+
+impl ConsoleMessage {
+    pub fn is_error(&self) -> bool {
+        matches!(self.level.as_str(), "error" | "assert" | "exception")
+    }
+}
+
+/// Subresource of a page which failed to load, or was served with a non-2xx status code.
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedResource {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct RequestInfo {
+    url: String,
+    is_main_document: bool,
+}
+
+#[derive(Default)]
+struct PageEvents {
+    main_document_status: Option<i64>,
+    console_messages: Vec<ConsoleMessage>,
+    requests: HashMap<String, RequestInfo>,
+    /// Request ids of the failed loads, with their error. They are only resolved to urls once
+    /// read, as the events of a request may be handled in any order by the listeners.
+    loading_failures: Vec<(String, String)>,
+    unsuccessful_responses: Vec<FailedResource>,
+}
+
+/// Collects the events emitted by a page while it loads, so that the conversion can be checked
+/// once the page is loaded. Collection stops when the monitor is dropped.
+pub struct PageMonitor {
+    events: Arc<Mutex<PageEvents>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl PageMonitor {
+    pub async fn attach(page: &Page) -> anyhow::Result<Self> {
+        let events = Arc::new(Mutex::new(PageEvents::default()));
+        let mut handles = Vec::new();
+
+        let main_frame_id = page.target_id().as_ref().to_string();
+        let mut responses = page.event_listener::<EventResponseReceived>().await?;
+        let response_events = events.clone();
+        handles.push(tokio::spawn(async move {
+            while let Some(event) = responses.next().await {
+                let is_main_document = event.r#type == ResourceType::Document
+                    && event
+                        .frame_id
+                        .as_ref()
+                        .is_some_and(|frame_id| frame_id.as_ref() == main_frame_id);
+
+                let mut events = response_events.lock().unwrap();
+                if is_main_document {
+                    // Redirects emit a response for each hop, the last one is the final document.
+                    events.main_document_status = Some(event.response.status);
+                } else if !(200..300).contains(&event.response.status) {
+                    events.unsuccessful_responses.push(FailedResource {
+                        url: event.response.url.clone(),
+                        status: Some(event.response.status),
+                        error: None,
+                    });
+                }
+            }
+        }));
+
+        let main_frame_id = page.target_id().as_ref().to_string();
+        let mut requests = page.event_listener::<EventRequestWillBeSent>().await?;
+        let request_events = events.clone();
+        handles.push(tokio::spawn(async move {
+            while let Some(event) = requests.next().await {
+                let is_main_document = event.r#type == Some(ResourceType::Document)
+                    && event
+                        .frame_id
+                        .as_ref()
+                        .is_some_and(|frame_id| frame_id.as_ref() == main_frame_id);
+
+                request_events.lock().unwrap().requests.insert(
+                    event.request_id.inner().clone(),
+                    RequestInfo {
+                        url: event.request.url.clone(),
+                        is_main_document,
+                    },
+                );
+            }
+        }));
+
+        let mut loading_failures = page.event_listener::<EventLoadingFailed>().await?;
+        let loading_failure_events = events.clone();
+        handles.push(tokio::spawn(async move {
+            while let Some(event) = loading_failures.next().await {
+                // Canceled loads, such as the ones of a page navigating away, are not failures.
+                // Neither are the requests blocked on purpose by the blocklists.
+                if event.canceled.unwrap_or(false) || event.error_text == BLOCKED_BY_CLIENT_ERROR {
+                    continue;
+                }
+
+                loading_failure_events
+                    .lock()
+                    .unwrap()
+                    .loading_failures
+                    .push((event.request_id.inner().clone(), event.error_text.clone()));
+            }
+        }));
+
+        let mut console_calls = page.event_listener::<EventConsoleApiCalled>().await?;
+        let console_events = events.clone();
+        handles.push(tokio::spawn(async move {
+            while let Some(event) = console_calls.next().await {
+                let level = match event.r#type {
+                    ConsoleApiCalledType::Warning => "warning",
+                    ref call_type => call_type.as_ref(),
+                };
+
+                let text = event
+                    .args
+                    .iter()
+                    .map(remote_object_to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                console_events
+                    .lock()
+                    .unwrap()
+                    .console_messages
+                    .push(ConsoleMessage {
+                        level: level.to_string(),
+                        text,
+                    });
+            }
+        }));
+
+        let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+        let exception_events = events.clone();
+        handles.push(tokio::spawn(async move {
+            while let Some(event) = exceptions.next().await {
+                let details = &event.exception_details;
+                let text = details
+                    .exception
+                    .as_ref()
+                    .and_then(|exception| exception.description.clone())
+                    .unwrap_or_else(|| details.text.clone());
+
+                exception_events
+                    .lock()
+                    .unwrap()
+                    .console_messages
+                    .push(ConsoleMessage {
+                        level: "exception".to_string(),
+                        text,
+                    });
+            }
+        }));
+
+        Ok(Self { events, handles })
+    }
+
+    pub fn main_document_status(&self) -> Option<i64> {
+        self.events.lock().unwrap().main_document_status
+    }
+
+    pub fn console_messages(&self) -> Vec<ConsoleMessage> {
+        self.events.lock().unwrap().console_messages.clone()
+    }
+
+    /// Subresources which failed to load so far. Failures of the main document are excluded, as
+    /// they already fail the navigation.
+    pub fn failed_resources(&self) -> Vec<FailedResource> {
+        let events = self.events.lock().unwrap();
+
+        let loading_failures = events
+            .loading_failures
+            .iter()
+            .filter_map(|(request_id, error)| {
+                let request = events.requests.get(request_id);
+                if request.is_some_and(|request| request.is_main_document) {
+                    return None;
+                }
+
+                Some(FailedResource {
+                    url: request
+                        .map(|request| request.url.clone())
+                        .unwrap_or_default(),
+                    status: None,
+                    error: Some(error.clone()),
+                })
+            });
+
+        events
+            .unsuccessful_responses
+            .iter()
+            .cloned()
+            .chain(loading_failures)
+            .collect()
+    }
+}
+
+impl Drop for PageMonitor {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+fn remote_object_to_string(object: &RemoteObject) -> String {
+    match &object.value {
+        Some(serde_json::Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+        None => object
+            .description
+            .clone()
+            .or_else(|| {
+                object
+                    .unserializable_value
+                    .as_ref()
+                    .map(|value| value.as_ref().to_string())
+            })
+            .unwrap_or_else(|| object.r#type.as_ref().to_string()),
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use chromiumoxide::cdp::browser_protocol::{
+    network::{self, CookieParam, ResourceType},
+    page::{CaptureScreenshotFormat, PrintToPdfParams, PrintToPdfReturns},
+};
+use chromiumoxide::types::MethodId;
+use chromiumoxide::{Command, Method};
+use serde::{Deserialize, Serialize};
+
+use super::UrlPattern;
+
+/// Options related to loading a page, shared by every kind of output.
+#[derive(Debug, Default)]
+pub struct PageOptions {
+    /// Directory of the uploaded files the page is loaded from, for html conversions.
+    pub local_directory: Option<PathBuf>,
+    pub min_page_load_time_ms: Option<u64>,
+    pub max_page_load_time_ms: Option<u64>,
+
+    pub extra_http_headers: HashMap<String, String>,
+    /// Only send the extra http headers to the origin of the converted url.
+    pub extra_http_headers_origin_only: bool,
+    pub cookies: Vec<Cookie>,
+
+    pub emulated_media_type: Option<MediaType>,
+    pub emulated_media_features: Vec<MediaFeature>,
+
+    /// Css selector that must match an element before the page is considered loaded.
+    pub wait_for_selector: Option<String>,
+    /// Javascript expression that must be truthy before the page is considered loaded.
+    pub wait_for_expression: Option<String>,
+
+    /// Fail the conversion when the main document is served with one of those status codes.
+    pub fail_on_http_status_codes: HttpStatusCodes,
+
+    /// Fail the conversion when the page logs a console error or throws an uncaught exception.
+    pub fail_on_console_errors: bool,
+
+    /// Fail the conversion when a subresource fails to load, or is served with a non-2xx status.
+    pub fail_on_resource_loading_failed: bool,
+    /// Urls of the subresources whose loading failures are ignored.
+    pub ignored_resource_loading_failures: Vec<UrlPattern>,
+
+    /// Types of the subresources which are not loaded, such as fonts or media.
+    pub blocked_resource_types: ResourceTypes,
+    /// Urls which are not loaded, on top of the default blocklist of the server.
+    pub blocked_urls: Vec<UrlPattern>,
+    /// Do not apply the default blocklist of the server.
+    pub skip_default_blocklist: bool,
+
+    /// Stylesheets added to the page once it is loaded.
+    pub inject_css: Vec<String>,
+    /// Scripts run in the page, at the time set by `inject_js_timing`.
+    pub inject_js: Vec<String>,
+    pub inject_js_timing: ScriptTiming,
+
+    /// Viewport and device the page is rendered with, instead of chromium's defaults.
+    pub device_metrics: Option<DeviceMetrics>,
+
+    /// IANA time zone of the page, such as `Europe/Paris`.
+    pub timezone: Option<String>,
+    /// ICU locale of the page, such as `fr-CA`, used by `Intl` and date formatting.
+    pub locale: Option<String>,
+    /// Value of the `Accept-Language` header and `navigator.languages`. Defaults to the locale.
+    pub accept_language: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Emulated device metrics. A width, height or scale factor of 0 keeps chromium's default value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceMetrics {
+    pub width: i64,
+    pub height: i64,
+    pub device_scale_factor: f64,
+    pub mobile: bool,
+}
+
+/// Named device, whose metrics are used as the base of the emulated metrics.
+#[derive(Clone, Copy)]
+pub enum DevicePreset {
+    IphoneSe,
+    Iphone14,
+    Iphone14ProMax,
+    Pixel7,
+    GalaxyS20,
+    Ipad,
+    IpadPro,
+    Laptop,
+    Desktop,
+}
+
+impl DevicePreset {
+    pub fn metrics(&self) -> DeviceMetrics {
+        let (width, height, device_scale_factor, mobile) = match self {
+            Self::IphoneSe => (375, 667, 2., true),
+            Self::Iphone14 => (390, 844, 3., true),
+            Self::Iphone14ProMax => (430, 932, 3., true),
+            Self::Pixel7 => (412, 915, 2.625, true),
+            Self::GalaxyS20 => (360, 800, 3., true),
+            Self::Ipad => (810, 1080, 2., true),
+            Self::IpadPro => (1024, 1366, 2., true),
+            Self::Laptop => (1366, 768, 1., false),
+            Self::Desktop => (1920, 1080, 1., false),
+        };
+
+        DeviceMetrics {
+            width,
+            height,
+            device_scale_factor,
+            mobile,
+        }
+    }
+}
+
+impl FromStr for DevicePreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "iphone-se" => Ok(Self::IphoneSe),
+            "iphone-14" => Ok(Self::Iphone14),
+            "iphone-14-pro-max" => Ok(Self::Iphone14ProMax),
+            "pixel-7" => Ok(Self::Pixel7),
+            "galaxy-s20" => Ok(Self::GalaxyS20),
+            "ipad" => Ok(Self::Ipad),
+            "ipad-pro" => Ok(Self::IpadPro),
+            "laptop" => Ok(Self::Laptop),
+            "desktop" => Ok(Self::Desktop),
+            _ => Err(anyhow!("unknown device `{s}`")),
+        }
+    }
+}
+
+/// When the injected scripts run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScriptTiming {
+    /// Before any of the page's scripts, in every document the page loads.
+    DocumentStart,
+    /// Once the page is loaded.
+    #[default]
+    Load,
+}
+
+/// Set of resource types, parsed from a comma separated list of CDP resource types, such as
+/// `font,media`.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceTypes(Vec<ResourceType>);
+
+impl ResourceTypes {
+    pub fn contains(&self, resource_type: &ResourceType) -> bool {
+        self.0.contains(resource_type)
+    }
+}
+
+impl FromStr for ResourceTypes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|resource_type| !resource_type.is_empty())
+            .map(|resource_type| {
+                resource_type
+                    .to_ascii_lowercase()
+                    .parse()
+                    .map_err(|_| anyhow!("invalid resource type `{resource_type}`"))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+}
+
+/// Set of http status codes, parsed from a comma separated list of codes and ranges, such as
+/// `404,5xx`.
+#[derive(Debug, Clone, Default)]
+pub struct HttpStatusCodes(Vec<RangeInclusive<i64>>);
+
+impl HttpStatusCodes {
+    pub fn contains(&self, status: i64) -> bool {
+        self.0.iter().any(|range| range.contains(&status))
+    }
+}
+
+impl FromStr for HttpStatusCodes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges = Vec::new();
+
+        for code in s.split(',').map(str::trim).filter(|code| !code.is_empty()) {
+            let range = match code.to_ascii_lowercase().strip_suffix("xx") {
+                Some(class) => match class.parse::<i64>() {
+                    Ok(class @ 1..=5) => class * 100..=class * 100 + 99,
+                    _ => return Err(anyhow!("invalid status code range `{code}`")),
+                },
+                None => match code.parse::<i64>() {
+                    Ok(status @ 100..=599) => status..=status,
+                    _ => return Err(anyhow!("invalid status code `{code}`")),
+                },
+            };
+
+            ranges.push(range);
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MediaType {
+    Print,
+    Screen,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Print => "print",
+            Self::Screen => "screen",
+        }
+    }
+}
+
+/// CSS media feature, such as `prefers-color-scheme`, and the value it is emulated with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaFeature {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<CookieSameSite> for network::CookieSameSite {
+    fn from(value: CookieSameSite) -> Self {
+        match value {
+            CookieSameSite::Strict => Self::Strict,
+            CookieSameSite::Lax => Self::Lax,
+            CookieSameSite::None => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub secure: Option<bool>,
+    pub http_only: Option<bool>,
+    pub same_site: Option<CookieSameSite>,
+}
+
+impl Cookie {
+    /// Cookies without a domain are bound to the given url.
+    pub fn to_cookie_param(&self, url: &str) -> CookieParam {
+        CookieParam {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            url: self.domain.is_none().then(|| url.to_string()),
+            domain: self.domain.clone(),
+            path: self.path.clone(),
+            secure: self.secure,
+            http_only: self.http_only,
+            same_site: self.same_site.map(Into::into),
+            expires: None,
+            priority: None,
+            same_party: None,
+            source_scheme: None,
+            source_port: None,
+            partition_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GeneratePdfOptions {
+    pub landscape: Option<bool>,
+    pub display_header_footer: Option<bool>,
+    pub print_background: Option<bool>,
+    pub scale: Option<f64>,
+    pub paper_width: Option<f64>,
+    pub paper_height: Option<f64>,
+    pub margin_top: Option<f64>,
+    pub margin_bottom: Option<f64>,
+    pub margin_left: Option<f64>,
+    pub margin_right: Option<f64>,
+    pub page_range: Option<String>,
+    pub header_template: Option<String>,
+    pub footer_template: Option<String>,
+    pub prefer_css_page_size: Option<bool>,
+    /// Generate a tagged, accessible, pdf.
+    pub generate_tagged_pdf: Option<bool>,
+    /// Embed an outline of the document, built from its headings, into the pdf.
+    pub generate_document_outline: Option<bool>,
+}
+
+/// Named paper size.
+#[derive(Clone, Copy)]
+pub enum PaperFormat {
+    A3,
+    A4,
+    A5,
+    Letter,
+    Legal,
+    Tabloid,
+}
+
+impl PaperFormat {
+    /// Width and height of the paper, in portrait orientation.
+    pub fn size(&self) -> (Length, Length) {
+        let (width, height) = match self {
+            Self::A3 => ("297mm", "420mm"),
+            Self::A4 => ("210mm", "297mm"),
+            Self::A5 => ("148mm", "210mm"),
+            Self::Letter => ("8.5in", "11in"),
+            Self::Legal => ("8.5in", "14in"),
+            Self::Tabloid => ("11in", "17in"),
+        };
+
+        (
+            width.parse().expect("paper width is valid"),
+            height.parse().expect("paper height is valid"),
+        )
+    }
+}
+
+impl FromStr for PaperFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "a3" => Ok(Self::A3),
+            "a4" => Ok(Self::A4),
+            "a5" => Ok(Self::A5),
+            "letter" => Ok(Self::Letter),
+            "legal" => Ok(Self::Legal),
+            "tabloid" => Ok(Self::Tabloid),
+            _ => Err(anyhow!("unknown paper format `{s}`")),
+        }
+    }
+}
+
+/// Length on the printed page, such as `210mm`. Parsed from a number followed by a
+/// `mm`, `cm`, `in`, `pt` or `px` unit, with numbers without unit being inches.
+#[derive(Clone, Copy)]
+pub struct Length {
+    inches: f64,
+}
+
+impl Length {
+    pub fn inches(&self) -> f64 {
+        self.inches
+    }
+}
+
+impl FromStr for Length {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unit_start = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(unit_start);
+
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| anyhow!("invalid length `{s}`"))?;
+        if !value.is_finite() || value < 0. {
+            return Err(anyhow!("length `{s}` must not be negative"));
+        }
+
+        let inches_per_unit = match unit.to_ascii_lowercase().as_str() {
+            "" | "in" => 1.,
+            "cm" => 1. / 2.54,
+            "mm" => 1. / 25.4,
+            "pt" => 1. / 72.,
+            "px" => 1. / 96.,
+            _ => {
+                return Err(anyhow!(
+                    "unknown unit `{unit}`, expected mm, cm, in, pt or px"
+                ))
+            }
+        };
+
+        Ok(Self {
+            inches: value * inches_per_unit,
+        })
+    }
+}
+
+/// `Page.printToPDF` command, with the parameters which are missing from [PrintToPdfParams].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintToPdfCommand {
+    #[serde(flatten)]
+    pub params: PrintToPdfParams,
+    #[serde(rename = "generateTaggedPDF", skip_serializing_if = "Option::is_none")]
+    pub generate_tagged_pdf: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generate_document_outline: Option<bool>,
+}
+
+impl Method for PrintToPdfCommand {
+    fn identifier(&self) -> MethodId {
+        PrintToPdfParams::IDENTIFIER.into()
+    }
+}
+
+impl Command for PrintToPdfCommand {
+    type Response = PrintToPdfReturns;
+}
+
+impl From<&GeneratePdfOptions> for PrintToPdfCommand {
+    fn from(value: &GeneratePdfOptions) -> Self {
+        let params = PrintToPdfParams {
+            landscape: value.landscape,
+            display_header_footer: value.display_header_footer,
+            print_background: value.print_background,
+            scale: value.scale,
+            paper_width: value.paper_width,
+            paper_height: value.paper_height,
+            margin_top: value.margin_top,
+            margin_bottom: value.margin_bottom,
+            margin_left: value.margin_left,
+            margin_right: value.margin_right,
+            page_ranges: value.page_range.clone(),
+            header_template: value.header_template.clone(),
+            footer_template: value.footer_template.clone(),
+            prefer_css_page_size: value.prefer_css_page_size,
+            transfer_mode: None,
+        };
+
+        Self {
+            params,
+            generate_tagged_pdf: value.generate_tagged_pdf,
+            generate_document_outline: value.generate_document_outline,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ScreenshotFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+impl From<ScreenshotFormat> for CaptureScreenshotFormat {
+    fn from(value: ScreenshotFormat) -> Self {
+        match value {
+            ScreenshotFormat::Png => Self::Png,
+            ScreenshotFormat::Jpeg => Self::Jpeg,
+            ScreenshotFormat::Webp => Self::Webp,
+        }
+    }
+}
+
+pub struct ScreenshotClip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Default)]
+pub struct CaptureScreenshotOptions {
+    pub format: ScreenshotFormat,
+    pub quality: Option<i64>,
+    pub full_page: Option<bool>,
+    pub clip: Option<ScreenshotClip>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chromiumoxide::cdp::browser_protocol::page::PrintToPdfTransferMode;
+
+    use super::*;
+
+    #[test]
+    fn serializes_print_to_pdf_parameters_with_their_cdp_names() {
+        let options = GeneratePdfOptions {
+            landscape: Some(true),
+            paper_width: Some(8.5),
+            page_range: Some("1-2".to_string()),
+            generate_tagged_pdf: Some(true),
+            generate_document_outline: Some(true),
+            ..Default::default()
+        };
+        let mut command = PrintToPdfCommand::from(&options);
+        command.params.transfer_mode = Some(PrintToPdfTransferMode::ReturnAsStream);
+
+        let json = serde_json::to_value(&command).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "landscape": true,
+                "paperWidth": 8.5,
+                "pageRanges": "1-2",
+                "transferMode": "ReturnAsStream",
+                "generateTaggedPDF": true,
+                "generateDocumentOutline": true,
+            })
+        );
+    }
+
+    #[test]
+    fn serializes_disabled_pdf_tagging() {
+        let options = GeneratePdfOptions {
+            generate_tagged_pdf: Some(false),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(PrintToPdfCommand::from(&options)).unwrap();
+        assert_eq!(json, serde_json::json!({ "generateTaggedPDF": false }));
+    }
+
+    #[test]
+    fn parses_status_codes_and_ranges() {
+        let codes: HttpStatusCodes = "404, 5xx,418".parse().unwrap();
+
+        for status in [404, 418, 500, 503, 599] {
+            assert!(codes.contains(status), "{status} should be contained");
+        }
+        for status in [200, 403, 405, 499, 600] {
+            assert!(!codes.contains(status), "{status} should not be contained");
+        }
+    }
+
+    #[test]
+    fn parses_uppercase_ranges_and_empty_lists() {
+        let codes: HttpStatusCodes = "4XX".parse().unwrap();
+        assert!(codes.contains(400) && codes.contains(499));
+
+        let codes: HttpStatusCodes = " , ".parse().unwrap();
+        assert!(!codes.contains(500));
+    }
+
+    #[test]
+    fn rejects_invalid_status_codes() {
+        for codes in ["abc", "99", "600", "6xx", "0xx", "x", "4x", "404,oops"] {
+            assert!(
+                codes.parse::<HttpStatusCodes>().is_err(),
+                "`{codes}` should be rejected"
+            );
+        }
+    }
+
+    fn inches(length: &str) -> f64 {
+        length.parse::<Length>().unwrap().inches()
+    }
+
+    fn assert_inches(length: &str, expected: f64) {
+        let inches = inches(length);
+        assert!(
+            (inches - expected).abs() < 1e-9,
+            "`{length}` is {inches} inches instead of {expected}"
+        );
+    }
+
+    #[test]
+    fn parses_lengths_with_units() {
+        assert_inches("8.5", 8.5);
+        assert_inches("8.5in", 8.5);
+        assert_inches("2.54cm", 1.);
+        assert_inches("25.4mm", 1.);
+        assert_inches("72pt", 1.);
+        assert_inches("96px", 1.);
+        assert_inches(" 210 MM ", 210. / 25.4);
+        assert_inches("0", 0.);
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        for length in ["", "mm", "-1mm", "-0.5", "1km", "inf", "NaN", "1 2mm"] {
+            assert!(
+                length.parse::<Length>().is_err(),
+                "`{length}` should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn paper_formats_are_portrait_sizes() {
+        let (width, height) = "a4".parse::<PaperFormat>().unwrap().size();
+        assert!((width.inches() - 210. / 25.4).abs() < 1e-9);
+        assert!((height.inches() - 297. / 25.4).abs() < 1e-9);
+
+        let (width, height) = "Letter".parse::<PaperFormat>().unwrap().size();
+        assert_eq!((width.inches(), height.inches()), (8.5, 11.));
+
+        assert!("b5".parse::<PaperFormat>().is_err());
+    }
+}
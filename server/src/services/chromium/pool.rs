@@ -0,0 +1,314 @@
+use std::ops::{Deref, Range};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chromiumoxide::{
+    cdp::browser_protocol::target::CreateBrowserContextParams, error::CdpError, handler::Handler,
+    Browser, BrowserConfig,
+};
+use futures::{future::try_join_all, StreamExt};
+use tokio::task::JoinHandle;
+
+use super::config::{BrowserSource, LaunchSettings};
+use super::error::ConversionError;
+
+const MIN_RELAUNCH_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RELAUNCH_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Features disabled on launch. Site isolation is disabled so that cross-site iframes are kept in
+/// the process of their page, since requests are only intercepted on the page's target. The
+/// default disabled features are repeated, since the flag replaces them.
+const DISABLED_FEATURES: &str = "TranslateUI,IsolateOrigins,site-per-process";
+
+/// Launches the instance, or connects to it when it is a remote one. Remote urls may be either
+/// the DevTools WebSocket url, or an http url whose `/json/version` endpoint gives it.
+async fn start_browser(
+    source: &BrowserSource,
+    index: usize,
+    launch: usize,
+) -> anyhow::Result<(Browser, Handler)> {
+    match source {
+        BrowserSource::Launch(settings) => launch_browser(settings, index, launch).await,
+        BrowserSource::Remote(urls) => Ok(Browser::connect(urls[index].as_str()).await?),
+    }
+}
+
+/// User data directory of a launch of the instance. Chromium locks its user data directory, which
+/// can therefore neither be shared by instances, nor reused by a relaunch while the previous
+/// process, which may have hung rather than exited, still holds the lock.
+fn user_data_dir(settings: &LaunchSettings, index: usize, launch: usize) -> Option<PathBuf> {
+    let user_data_dir = settings.user_data_dir.as_ref()?;
+    Some(user_data_dir.join(format!("instance-{index}-{launch}")))
+}
+
+async fn launch_browser(
+    settings: &LaunchSettings,
+    index: usize,
+    launch: usize,
+) -> anyhow::Result<(Browser, Handler)> {
+    let mut builder = BrowserConfig::builder()
+        .chrome_executable(&settings.executable)
+        .arg("--disable-site-isolation-trials")
+        .arg(format!("--disable-features={DISABLED_FEATURES}"))
+        .args(&settings.args)
+        .launch_timeout(settings.launch_timeout);
+
+    if !settings.sandbox {
+        builder = builder.no_sandbox();
+    }
+    if let Some(user_data_dir) = user_data_dir(settings, index, launch) {
+        builder = builder.user_data_dir(user_data_dir);
+    }
+    if let Some(window_size) = settings.window_size {
+        builder = builder.window_size(window_size.width, window_size.height);
+    }
+
+    let (browser, handler) = Browser::launch(builder.build().map_err(|err| anyhow!(err))?).await?;
+
+    Ok((browser, handler))
+}
+
+struct BrowserInstance {
+    index: usize,
+    source: Arc<BrowserSource>,
+    /// Url of the proxy the requests of the browser contexts are sent through.
+    proxy_server: Arc<str>,
+    browser: RwLock<Arc<Browser>>,
+    /// Whether the browser is running, as opposed to being relaunched.
+    healthy: AtomicBool,
+    active_contexts: AtomicUsize,
+    restarts: AtomicUsize,
+    /// Number of the last attempt to launch the browser, or to connect to it.
+    launch: AtomicUsize,
+}
+
+impl BrowserInstance {
+    fn browser(&self) -> Arc<Browser> {
+        self.browser.read().unwrap().clone()
+    }
+
+    /// Drives the handler of the current browser, and relaunches chromium, or reconnects to it,
+    /// whenever the connection to the browser is lost. Requests that were using the dead browser
+    /// fail, since their commands can no longer be answered.
+    async fn supervise(self: Arc<Self>, mut handler: Handler) {
+        loop {
+            drive_handler(self.index, &mut handler).await;
+
+            tracing::error!(instance = self.index, "chromium instance died, restarting");
+            self.healthy.store(false, Ordering::Relaxed);
+            // The pending commands of the dead browser only fail once its handler is dropped,
+            // which must not wait for the relaunch.
+            drop(handler);
+
+            handler = self.relaunch().await;
+            self.healthy.store(true, Ordering::Relaxed);
+
+            let restarts = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::info!(
+                instance = self.index,
+                restarts,
+                "chromium instance restarted"
+            );
+        }
+    }
+
+    async fn relaunch(&self) -> Handler {
+        let mut backoff = MIN_RELAUNCH_BACKOFF;
+        let previous_launch = self.launch.load(Ordering::Relaxed);
+
+        loop {
+            let launch = self.launch.fetch_add(1, Ordering::Relaxed) + 1;
+
+            match start_browser(&self.source, self.index, launch).await {
+                Ok((browser, handler)) => {
+                    *self.browser.write().unwrap() = Arc::new(browser);
+                    self.remove_user_data_dirs(previous_launch..launch).await;
+                    return handler;
+                }
+                Err(err) => {
+                    tracing::error!(
+                        instance = self.index,
+                        "could not restart chromium instance: {err}"
+                    );
+
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_RELAUNCH_BACKOFF);
+                }
+            }
+        }
+    }
+
+    /// Removes the user data directories of the previous launches, once they are replaced.
+    async fn remove_user_data_dirs(&self, launches: Range<usize>) {
+        let BrowserSource::Launch(settings) = self.source.as_ref() else {
+            return;
+        };
+
+        for launch in launches {
+            let Some(user_data_dir) = user_data_dir(settings, self.index, launch) else {
+                return;
+            };
+
+            match tokio::fs::remove_dir_all(&user_data_dir).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => tracing::warn!(
+                    instance = self.index,
+                    "could not remove user data directory {}: {err}",
+                    user_data_dir.display()
+                ),
+            }
+        }
+    }
+}
+
+/// Polls the handler until the browser is considered dead, either because the connection was
+/// closed or because the connection returned an error that cannot be recovered from.
+async fn drive_handler(index: usize, handler: &mut Handler) {
+    while let Some(result) = handler.next().await {
+        match result {
+            Ok(()) => {}
+            Err(err @ (CdpError::Ws(_) | CdpError::Io(_) | CdpError::ChannelSendError(_))) => {
+                tracing::error!(instance = index, "chromium connection error: {err}");
+                return;
+            }
+            Err(err) => {
+                tracing::warn!(instance = index, "chromium handler error: {err}");
+            }
+        }
+    }
+}
+
+/// A fixed set of chromium instances. Work is dispatched to the instance with the least
+/// amount of active browser contexts.
+pub struct BrowserPool {
+    instances: Vec<Arc<BrowserInstance>>,
+    supervisors: Vec<JoinHandle<()>>,
+}
+
+impl BrowserPool {
+    pub async fn launch(
+        size: usize,
+        source: BrowserSource,
+        proxy_server: &str,
+    ) -> anyhow::Result<Self> {
+        let source = Arc::new(source);
+        let proxy_server: Arc<str> = Arc::from(proxy_server);
+        let launched =
+            try_join_all((0..size).map(|index| start_browser(&source, index, 0))).await?;
+
+        let mut instances = Vec::with_capacity(size);
+        let mut supervisors = Vec::with_capacity(size);
+
+        for (index, (browser, handler)) in launched.into_iter().enumerate() {
+            let instance = Arc::new(BrowserInstance {
+                index,
+                source: source.clone(),
+                proxy_server: proxy_server.clone(),
+                browser: RwLock::new(Arc::new(browser)),
+                healthy: AtomicBool::new(true),
+                active_contexts: AtomicUsize::new(0),
+                restarts: AtomicUsize::new(0),
+                launch: AtomicUsize::new(0),
+            });
+
+            supervisors.push(tokio::spawn(instance.clone().supervise(handler)));
+            instances.push(instance);
+        }
+
+        Ok(Self {
+            instances,
+            supervisors,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn source(&self) -> &BrowserSource {
+        &self.instances[0].source
+    }
+
+    /// Product name and version of chromium, such as `HeadlessChrome/120.0.6099.109`.
+    pub async fn version(&self) -> anyhow::Result<String> {
+        let version = self.instances[0].browser().version().await?;
+        Ok(version.product)
+    }
+
+    /// Total amount of times chromium instances had to be relaunched, or reconnected to.
+    pub fn restarts(&self) -> usize {
+        self.instances
+            .iter()
+            .map(|instance| instance.restarts.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Leases the healthy instance with the least amount of active browser contexts. Fails when
+    /// every instance is being relaunched.
+    pub fn acquire(&self) -> Result<BrowserLease, ConversionError> {
+        let instance = self
+            .instances
+            .iter()
+            .filter(|instance| instance.healthy.load(Ordering::Relaxed))
+            .min_by_key(|instance| instance.active_contexts.load(Ordering::Relaxed))
+            .ok_or(ConversionError::BrowserUnavailable)?;
+
+        instance.active_contexts.fetch_add(1, Ordering::Relaxed);
+
+        Ok(BrowserLease {
+            browser: instance.browser(),
+            instance: instance.clone(),
+        })
+    }
+}
+
+impl Drop for BrowserPool {
+    fn drop(&mut self) {
+        for supervisor in &self.supervisors {
+            supervisor.abort();
+        }
+    }
+}
+
+/// Handle on one of the pool's browsers. The browser is considered busy until the lease is
+/// dropped.
+pub struct BrowserLease {
+    browser: Arc<Browser>,
+    instance: Arc<BrowserInstance>,
+}
+
+impl BrowserLease {
+    /// Whether the leased browser is still the running browser of its instance.
+    pub fn is_current(&self) -> bool {
+        Arc::ptr_eq(&self.browser, &self.instance.browser())
+    }
+
+    /// Parameters of the browser contexts created on the browser. Their requests are sent through
+    /// the proxy, loopback ones included, which chromium does not proxy by default.
+    pub fn browser_context_params(&self) -> CreateBrowserContextParams {
+        CreateBrowserContextParams::builder()
+            .proxy_server(self.instance.proxy_server.as_ref())
+            .proxy_bypass_list("<-loopback>")
+            .build()
+    }
+}
+
+impl Deref for BrowserLease {
+    type Target = Browser;
+
+    fn deref(&self) -> &Self::Target {
+        &self.browser
+    }
+}
+
+impl Drop for BrowserLease {
+    fn drop(&mut self) {
+        self.instance
+            .active_contexts
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
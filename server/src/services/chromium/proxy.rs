@@ -0,0 +1,185 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::{
+    client::conn as client_conn,
+    header::{HeaderName, PROXY_AUTHORIZATION},
+    http::uri::{PathAndQuery, Scheme},
+    server::conn::Http,
+    service::service_fn,
+    Body, Method, Request, Response, StatusCode, Uri,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::UrlPolicy;
+
+/// Http proxy the requests of the browser contexts are sent through. The proxy resolves the
+/// host names itself and only connects to the addresses it checked against the url policy, so
+/// that a host name cannot resolve to an allowed address when checked and to a denied one when
+/// requested.
+///
+/// It covers every request of a browser context, including the ones which are not intercepted,
+/// such as the requests of workers and WebSocket connections.
+pub struct UrlPolicyProxy {
+    /// Url chromium reaches the proxy at.
+    url: String,
+    handle: JoinHandle<()>,
+}
+
+impl UrlPolicyProxy {
+    pub async fn start(
+        address: SocketAddr,
+        url: Option<String>,
+        url_policy: Arc<UrlPolicy>,
+    ) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let url = match url {
+            Some(url) => url,
+            None => format!("http://{}", listener.local_addr()?),
+        };
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        tracing::warn!("could not accept proxy connection: {err}");
+                        continue;
+                    }
+                };
+
+                let url_policy = url_policy.clone();
+                tokio::spawn(async move {
+                    let service =
+                        service_fn(move |request| proxy_request(url_policy.clone(), request));
+                    let connection = Http::new()
+                        .http1_only(true)
+                        .serve_connection(stream, service)
+                        .with_upgrades();
+
+                    if let Err(err) = connection.await {
+                        tracing::debug!("proxy connection error: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self { url, handle })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for UrlPolicyProxy {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn proxy_request(
+    url_policy: Arc<UrlPolicy>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match forward_request(&url_policy, request).await {
+        Ok(response) => response,
+        Err((status, message)) => {
+            tracing::debug!("proxy request failed: {message}");
+
+            let mut response = Response::new(Body::from(message));
+            *response.status_mut() = status;
+            response
+        }
+    };
+
+    Ok(response)
+}
+
+/// Connects to the host of the request, and either tunnels the connection for `CONNECT`
+/// requests, such as the ones of https urls and WebSocket connections, or forwards the request
+/// for http urls.
+async fn forward_request(
+    url_policy: &UrlPolicy,
+    mut request: Request<Body>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let is_connect = request.method() == Method::CONNECT;
+    if !is_connect && request.uri().scheme() != Some(&Scheme::HTTP) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("cannot proxy {}", request.uri()),
+        ));
+    }
+
+    let Some(authority) = request.uri().authority().cloned() else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("missing host in {}", request.uri()),
+        ));
+    };
+    let default_port = if is_connect { 443 } else { 80 };
+
+    let addresses = url_policy
+        .resolve(
+            authority.host(),
+            authority.port_u16().unwrap_or(default_port),
+        )
+        .await
+        .map_err(|reason| (StatusCode::FORBIDDEN, reason))?;
+    let mut stream = TcpStream::connect(&addresses[..]).await.map_err(|err| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("could not connect to {authority}: {err}"),
+        )
+    })?;
+
+    if is_connect {
+        tokio::spawn(async move {
+            let result = match hyper::upgrade::on(request).await {
+                Ok(mut upgraded) => tokio::io::copy_bidirectional(&mut upgraded, &mut stream)
+                    .await
+                    .map(|_| ()),
+                Err(err) => Err(std::io::Error::other(err)),
+            };
+
+            if let Err(err) = result {
+                tracing::debug!("proxy tunnel to {authority} failed: {err}");
+            }
+        });
+
+        return Ok(Response::new(Body::empty()));
+    }
+
+    // Origin servers expect the path of the url, instead of the whole url sent to proxies.
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .cloned()
+        .unwrap_or_else(|| PathAndQuery::from_static("/"));
+    *request.uri_mut() = Uri::from(path_and_query);
+    request.headers_mut().remove(PROXY_AUTHORIZATION);
+    request
+        .headers_mut()
+        .remove(HeaderName::from_static("proxy-connection"));
+
+    let (mut sender, connection) = client_conn::handshake(stream).await.map_err(|err| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("could not connect to {authority}: {err}"),
+        )
+    })?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            tracing::debug!("proxy connection to origin server failed: {err}");
+        }
+    });
+
+    sender.send_request(request).await.map_err(|err| {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("request to {authority} failed: {err}"),
+        )
+    })
+}
@@ -0,0 +1,180 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::error::ConversionError;
+
+const RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Limits the amount of conversions running at the same time. Conversions over the limit wait in
+/// a bounded queue, and are rejected once the queue is full.
+pub struct ConversionQueue {
+    semaphore: Arc<Semaphore>,
+    max_concurrency: usize,
+    max_size: usize,
+    waiting: AtomicUsize,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+    total_wait_ms: AtomicU64,
+}
+
+pub struct ConversionQueueStats {
+    pub max_concurrency: usize,
+    pub max_size: usize,
+    pub active: usize,
+    pub waiting: usize,
+    pub rejected: u64,
+    pub average_wait_ms: u64,
+}
+
+impl ConversionQueue {
+    pub fn new(max_concurrency: usize, max_size: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency)),
+            max_concurrency,
+            max_size,
+            waiting: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            total_wait_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits for a free slot. The slot is released once the returned permit is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, ConversionError> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            self.admitted.fetch_add(1, Ordering::Relaxed);
+            return Ok(permit);
+        }
+
+        if self.waiting.fetch_add(1, Ordering::Relaxed) >= self.max_size {
+            self.waiting.fetch_sub(1, Ordering::Relaxed);
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+
+            return Err(ConversionError::QueueFull {
+                retry_after: RETRY_AFTER,
+            });
+        }
+
+        // Leaves the queue even if the request is cancelled while waiting.
+        let _waiting = WaitingGuard(&self.waiting);
+        let started_at = Instant::now();
+
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("conversion queue semaphore is never closed");
+
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_ms
+            .fetch_add(started_at.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+        Ok(permit)
+    }
+
+    pub fn stats(&self) -> ConversionQueueStats {
+        let admitted = self.admitted.load(Ordering::Relaxed);
+        let total_wait_ms = self.total_wait_ms.load(Ordering::Relaxed);
+
+        ConversionQueueStats {
+            max_concurrency: self.max_concurrency,
+            max_size: self.max_size,
+            active: self.max_concurrency - self.semaphore.available_permits(),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            average_wait_ms: total_wait_ms.checked_div(admitted).unwrap_or(0),
+        }
+    }
+}
+
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::poll;
+
+    #[tokio::test]
+    async fn admits_requests_while_slots_are_free() {
+        let queue = ConversionQueue::new(2, 0);
+
+        let _first = queue.acquire().await.unwrap();
+        let _second = queue.acquire().await.unwrap();
+
+        let stats = queue.stats();
+        assert_eq!(stats.active, 2);
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.rejected, 0);
+        assert_eq!(stats.average_wait_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_once_the_queue_is_full() {
+        let queue = ConversionQueue::new(1, 1);
+        let _permit = queue.acquire().await.unwrap();
+
+        let mut waiting = Box::pin(queue.acquire());
+        assert!(poll!(&mut waiting).is_pending());
+        assert_eq!(queue.stats().waiting, 1);
+
+        let rejected = queue.acquire().await;
+        assert!(matches!(
+            rejected,
+            Err(ConversionError::QueueFull { retry_after }) if retry_after == RETRY_AFTER
+        ));
+
+        let stats = queue.stats();
+        assert_eq!(stats.waiting, 1);
+        assert_eq!(stats.rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn leaves_the_queue_when_a_waiting_request_is_dropped() {
+        let queue = ConversionQueue::new(1, 1);
+        let _permit = queue.acquire().await.unwrap();
+
+        let mut waiting = Box::pin(queue.acquire());
+        assert!(poll!(&mut waiting).is_pending());
+        assert_eq!(queue.stats().waiting, 1);
+
+        drop(waiting);
+        assert_eq!(queue.stats().waiting, 0);
+
+        let mut waiting = Box::pin(queue.acquire());
+        assert!(poll!(&mut waiting).is_pending());
+        assert_eq!(queue.stats().rejected, 0);
+    }
+
+    #[tokio::test]
+    async fn admits_waiting_requests_once_a_slot_is_released() {
+        let queue = Arc::new(ConversionQueue::new(1, 1));
+        let permit = queue.acquire().await.unwrap();
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire().await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(queue.stats().waiting, 1);
+
+        drop(permit);
+        waiting.await.unwrap().unwrap();
+
+        let stats = queue.stats();
+        assert_eq!(stats.active, 0);
+        assert_eq!(stats.waiting, 0);
+        // The first request was admitted right away, and the second one waited at least 50ms.
+        assert!(stats.average_wait_ms >= 25);
+    }
+}
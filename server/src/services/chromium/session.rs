@@ -0,0 +1,168 @@
+use std::ops::Deref;
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bytes::Bytes;
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        browser::BrowserContextId,
+        io::{CloseParams, ReadParams, StreamHandle},
+        target::CreateTargetParams,
+    },
+    Browser, Page,
+};
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::sync::OwnedSemaphorePermit;
+
+use super::interception::InterceptionGuard;
+use super::monitor::PageMonitor;
+use super::pool::BrowserLease;
+
+/// Size of the chunks read from the streams returned by chromium.
+const STREAM_CHUNK_SIZE: i64 = 256 * 1024;
+
+pub type ConversionStream = BoxStream<'static, anyhow::Result<Bytes>>;
+
+/// Browser context of a conversion, on one of the pool's browsers. The context is disposed once
+/// the guard is dropped, unless it was already disposed.
+pub struct BrowserContextGuard {
+    context: Option<(BrowserLease, BrowserContextId)>,
+}
+
+impl BrowserContextGuard {
+    pub fn new(browser: BrowserLease, browser_context_id: BrowserContextId) -> Self {
+        Self {
+            context: Some((browser, browser_context_id)),
+        }
+    }
+
+    /// Creates a new browser context on the browser, along with a blank page in it.
+    pub async fn create(browser: BrowserLease) -> anyhow::Result<(Self, Page)> {
+        let browser_context_id = browser
+            .create_browser_context(browser.browser_context_params())
+            .await?;
+        let browser_context = Self::new(browser, browser_context_id.clone());
+
+        let new_page_params = CreateTargetParams::builder()
+            .url("about:blank")
+            .browser_context_id(browser_context_id)
+            .build()
+            .map_err(|err| anyhow!(err))?;
+
+        match browser_context.new_page(new_page_params).await {
+            Ok(page) => Ok((browser_context, page)),
+            Err(err) => {
+                if let Err(dispose_err) = browser_context.dispose().await {
+                    tracing::warn!("could not dispose browser context: {dispose_err}");
+                }
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// Whether the context belongs to the running browser of its instance, and not to one which
+    /// has since been relaunched.
+    pub fn is_current(&self) -> bool {
+        self.context
+            .as_ref()
+            .is_some_and(|(browser, _)| browser.is_current())
+    }
+
+    pub async fn dispose(mut self) -> anyhow::Result<()> {
+        if let Some((browser, browser_context_id)) = self.context.take() {
+            browser.dispose_browser_context(browser_context_id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Deref for BrowserContextGuard {
+    type Target = Browser;
+
+    fn deref(&self) -> &Self::Target {
+        let (browser, _) = self
+            .context
+            .as_ref()
+            .expect("browser context is not disposed");
+        browser
+    }
+}
+
+impl Drop for BrowserContextGuard {
+    fn drop(&mut self) {
+        let Some((browser, browser_context_id)) = self.context.take() else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            if let Err(err) = browser.dispose_browser_context(browser_context_id).await {
+                tracing::warn!("could not dispose browser context: {err}");
+            }
+        });
+    }
+}
+
+/// Loaded page of a conversion, along with everything that must live as long as the page does:
+/// its browser context, its queue slot, and the tasks handling its events.
+pub struct PageSession {
+    pub page: Page,
+    pub _interception: InterceptionGuard,
+    pub _monitor: PageMonitor,
+    pub browser_context: BrowserContextGuard,
+    pub _permit: OwnedSemaphorePermit,
+}
+
+impl PageSession {
+    /// Disposes the browser context, and releases the queue slot.
+    pub async fn close(self) -> anyhow::Result<()> {
+        self.browser_context.dispose().await
+    }
+
+    /// Reads the chromium stream in chunks. The session is closed once the stream is fully read,
+    /// or once the returned stream is dropped, such as when the client disconnects.
+    pub fn into_stream(self, handle: StreamHandle) -> ConversionStream {
+        stream::try_unfold(Some(self), move |session| {
+            let handle = handle.clone();
+
+            async move {
+                let Some(session) = session else {
+                    return Ok(None);
+                };
+
+                let read = session
+                    .page
+                    .execute(ReadParams {
+                        handle: handle.clone(),
+                        offset: None,
+                        size: Some(STREAM_CHUNK_SIZE),
+                    })
+                    .await?
+                    .result;
+
+                let chunk = match read.base64_encoded {
+                    Some(true) => Bytes::from(BASE64.decode(&read.data)?),
+                    _ => Bytes::from(read.data),
+                };
+
+                if !read.eof {
+                    return Ok(Some((chunk, Some(session))));
+                }
+
+                // The last chunk is yielded right away, and the session closed in the background,
+                // so that the client neither waits for nor loses its data to a failed disposal.
+                tokio::spawn(async move {
+                    if let Err(err) = session.page.execute(CloseParams::new(handle)).await {
+                        tracing::debug!("could not close chromium stream: {err}");
+                    }
+                    if let Err(err) = session.close().await {
+                        tracing::warn!("could not dispose browser context: {err}");
+                    }
+                });
+
+                Ok(Some((chunk, None)))
+            }
+        })
+        .boxed()
+    }
+}
@@ -0,0 +1,106 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Url glob pattern, where `*` matches any sequence of characters, including none.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "String")]
+pub struct UrlPattern(String);
+
+impl From<String> for UrlPattern {
+    fn from(pattern: String) -> Self {
+        Self(pattern)
+    }
+}
+
+impl FromStr for UrlPattern {
+    type Err = Infallible;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Self(pattern.to_string()))
+    }
+}
+
+impl UrlPattern {
+    pub fn matches(&self, url: &str) -> bool {
+        let pattern = self.0.as_bytes();
+        let url = url.as_bytes();
+
+        let (mut pattern_index, mut url_index) = (0, 0);
+        // Position of the last `*` in the pattern, and the url position it is currently matched to.
+        let mut backtrack = None;
+
+        while url_index < url.len() {
+            match pattern.get(pattern_index) {
+                Some(b'*') => {
+                    backtrack = Some((pattern_index, url_index));
+                    pattern_index += 1;
+                }
+                Some(&byte) if byte == url[url_index] => {
+                    pattern_index += 1;
+                    url_index += 1;
+                }
+                _ => match backtrack {
+                    Some((star_index, star_url_index)) => {
+                        backtrack = Some((star_index, star_url_index + 1));
+                        pattern_index = star_index + 1;
+                        url_index = star_url_index + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[pattern_index..].iter().all(|&byte| byte == b'*')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, url: &str) -> bool {
+        UrlPattern::from(pattern.to_string()).matches(url)
+    }
+
+    #[test]
+    fn matches_literal_patterns_exactly() {
+        assert!(matches("https://example.com/", "https://example.com/"));
+        assert!(!matches("https://example.com/", "https://example.com/a"));
+        assert!(!matches("https://example.com/a", "https://example.com/"));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn star_matches_any_sequence() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "https://example.com/"));
+        assert!(matches(
+            "https://*.example.com/*",
+            "https://cdn.example.com/a.js"
+        ));
+        assert!(matches(
+            "https://*.example.com/*",
+            "https://a.b.example.com/"
+        ));
+        assert!(!matches("https://*.example.com/*", "https://example.com/"));
+        assert!(matches("*.js", "https://example.com/a.js"));
+        assert!(!matches("*.js", "https://example.com/a.js?v=1"));
+    }
+
+    #[test]
+    fn star_backtracks_over_partial_matches() {
+        assert!(matches("*ab", "aab"));
+        assert!(matches("*ab*cd", "abxabycd"));
+        assert!(matches("a*b*c", "abbbc"));
+        assert!(!matches("a*b*c", "abbbd"));
+        assert!(matches("**a", "ba"));
+    }
+
+    #[test]
+    fn is_case_sensitive() {
+        assert!(!matches("https://Example.com/*", "https://example.com/"));
+    }
+}
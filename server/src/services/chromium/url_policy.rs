@@ -0,0 +1,438 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use ipnet::IpNet;
+use url::{Host, Url};
+
+use crate::utils::env::{parse_env_list, parse_env_var};
+
+use super::UrlPattern;
+
+const DEFAULT_ALLOWED_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Loopback, private, link-local and other non-public address ranges, which are denied unless
+/// explicitly allowed.
+const DEFAULT_DENIED_CIDRS: [&str; 18] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    // NAT64 and 6to4 addresses embed an IPv4 address, which may be a non-public one.
+    "64:ff9b::/96",
+    "2002::/16",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// Host name pattern, compared case-insensitively, where `*` matches any sequence of characters.
+#[derive(Clone)]
+pub struct HostPattern(UrlPattern);
+
+impl FromStr for HostPattern {
+    type Err = Infallible;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Self(UrlPattern::from(pattern.to_ascii_lowercase())))
+    }
+}
+
+/// Access to remote urls of the pages loaded from uploaded files.
+pub enum RemoteAccess {
+    Allowed,
+    Denied,
+    /// Only the urls matching one of the patterns are allowed.
+    AllowListed(Vec<UrlPattern>),
+}
+
+/// Restricts the urls a page is allowed to request, to prevent conversions from reaching the
+/// internal network of the server.
+pub struct UrlPolicy {
+    allowed_schemes: Vec<String>,
+    /// When not empty, only the hosts matching one of those patterns are allowed.
+    allowed_hosts: Vec<HostPattern>,
+    denied_hosts: Vec<HostPattern>,
+    /// Ranges allowed even though they are part of the denied ranges.
+    allowed_cidrs: Vec<IpNet>,
+    denied_cidrs: Vec<IpNet>,
+    local_remote_access: RemoteAccess,
+}
+
+impl UrlPolicy {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let allowed_schemes = parse_env_list::<String>("RUSTENBERG_URL_ALLOWED_SCHEMES")?
+            .unwrap_or_else(|| DEFAULT_ALLOWED_SCHEMES.map(String::from).to_vec())
+            .into_iter()
+            .map(|scheme| scheme.to_ascii_lowercase())
+            .collect();
+
+        let mut denied_cidrs = DEFAULT_DENIED_CIDRS
+            .iter()
+            .map(|cidr| cidr.parse())
+            .collect::<Result<Vec<IpNet>, _>>()?;
+        denied_cidrs
+            .extend(parse_env_list::<IpNet>("RUSTENBERG_URL_DENIED_CIDRS")?.unwrap_or_default());
+
+        let local_remote_access =
+            match parse_env_var::<String>("RUSTENBERG_HTML_REMOTE_ACCESS")?.as_deref() {
+                None | Some("allow") => RemoteAccess::Allowed,
+                Some("deny") => RemoteAccess::Denied,
+                Some("allowlist") => RemoteAccess::AllowListed(
+                    parse_env_list("RUSTENBERG_HTML_REMOTE_ALLOWLIST")?.unwrap_or_default(),
+                ),
+                Some(value) => {
+                    return Err(anyhow!(
+                        "invalid value for RUSTENBERG_HTML_REMOTE_ACCESS: {value}"
+                    ))
+                }
+            };
+
+        Ok(Self {
+            allowed_schemes,
+            allowed_hosts: parse_env_list("RUSTENBERG_URL_ALLOWED_HOSTS")?.unwrap_or_default(),
+            denied_hosts: parse_env_list("RUSTENBERG_URL_DENIED_HOSTS")?.unwrap_or_default(),
+            allowed_cidrs: parse_env_list("RUSTENBERG_URL_ALLOWED_CIDRS")?.unwrap_or_default(),
+            denied_cidrs,
+            local_remote_access,
+        })
+    }
+
+    /// Whether urls with the scheme may be requested, by a page loaded from the uploaded files
+    /// when `is_local` is set. Used for WebSocket urls, which cannot be checked one by one.
+    pub fn is_scheme_allowed(&self, scheme: &str, is_local: bool) -> bool {
+        let is_remote_allowed =
+            !is_local || matches!(self.local_remote_access, RemoteAccess::Allowed);
+
+        is_remote_allowed && self.allowed_schemes.iter().any(|allowed| allowed == scheme)
+    }
+
+    /// Checks that the url may be requested by a page loaded from the uploaded files of the
+    /// directory. Local files are only allowed inside of the directory, and remote urls are
+    /// subject to the remote access setting on top of the policy.
+    pub async fn check_local(&self, url: &str, directory: &Path) -> Result<(), String> {
+        if url.starts_with("file:") {
+            let path = Url::parse(url)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .ok_or_else(|| "invalid file url".to_string())?;
+
+            // Symbolic links and `..` components are resolved, so that they cannot escape the
+            // directory. Files that do not exist cannot be resolved, and are denied as well.
+            let is_inside_directory = tokio::fs::canonicalize(&path)
+                .await
+                .is_ok_and(|path| path.starts_with(directory));
+
+            return match is_inside_directory {
+                true => Ok(()),
+                false => Err("only the uploaded files are allowed".to_string()),
+            };
+        }
+
+        match &self.local_remote_access {
+            RemoteAccess::Allowed => {}
+            RemoteAccess::Denied => return Err("remote urls are denied".to_string()),
+            RemoteAccess::AllowListed(patterns) => {
+                if !patterns.iter().any(|pattern| pattern.matches(url)) {
+                    return Err("url is not part of the remote allow list".to_string());
+                }
+            }
+        }
+
+        self.check(url).await
+    }
+
+    /// Checks that the url may be requested, and returns the reason why it may not otherwise.
+    /// Host names are resolved, so that names pointing to denied addresses are denied as well.
+    pub async fn check(&self, url: &str) -> Result<(), String> {
+        let url = Url::parse(url).map_err(|err| format!("invalid url: {err}"))?;
+
+        if !self
+            .allowed_schemes
+            .iter()
+            .any(|scheme| scheme == url.scheme())
+        {
+            return Err(format!("scheme `{}` is not allowed", url.scheme()));
+        }
+
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        let port = url.port_or_known_default().unwrap_or(80);
+
+        self.resolve(host, port).await.map(|_| ())
+    }
+
+    /// Resolves the host, and returns its addresses when the host and all of its addresses are
+    /// allowed, or the reason why they are not otherwise.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+        let host = Host::parse(host).map_err(|err| format!("invalid host `{host}`: {err}"))?;
+
+        let host_name = match &host {
+            Host::Domain(domain) => domain.to_ascii_lowercase(),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+        };
+
+        if self
+            .denied_hosts
+            .iter()
+            .any(|pattern| pattern.0.matches(&host_name))
+        {
+            return Err(format!("host `{host_name}` is denied"));
+        }
+
+        if !self.allowed_hosts.is_empty()
+            && !self
+                .allowed_hosts
+                .iter()
+                .any(|pattern| pattern.0.matches(&host_name))
+        {
+            return Err(format!("host `{host_name}` is not allowed"));
+        }
+
+        let addresses: Vec<SocketAddr> = match host {
+            Host::Ipv4(ip) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+            Host::Ipv6(ip) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+            Host::Domain(domain) => tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|err| format!("could not resolve host `{host_name}`: {err}"))?
+                .collect(),
+        };
+
+        match addresses
+            .iter()
+            .find(|address| !self.is_ip_allowed(address.ip()))
+        {
+            Some(address) => Err(format!(
+                "address `{}` of host `{host_name}` is denied",
+                address.ip()
+            )),
+            None => Ok(addresses),
+        }
+    }
+
+    fn is_ip_allowed(&self, ip: IpAddr) -> bool {
+        // IPv4-mapped IPv6 addresses reach the IPv4 address, they are checked as such.
+        let ip = match ip {
+            IpAddr::V6(ipv6) => ipv6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+
+        self.allowed_cidrs.iter().any(|cidr| cidr.contains(&ip))
+            || !self.denied_cidrs.iter().any(|cidr| cidr.contains(&ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> UrlPolicy {
+        UrlPolicy {
+            allowed_schemes: DEFAULT_ALLOWED_SCHEMES.map(String::from).to_vec(),
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allowed_cidrs: Vec::new(),
+            denied_cidrs: DEFAULT_DENIED_CIDRS
+                .iter()
+                .map(|cidr| cidr.parse().unwrap())
+                .collect(),
+            local_remote_access: RemoteAccess::Allowed,
+        }
+    }
+
+    fn is_ip_allowed(policy: &UrlPolicy, ip: &str) -> bool {
+        policy.is_ip_allowed(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn denies_non_public_addresses() {
+        let policy = policy();
+
+        for ip in [
+            "0.0.0.0",
+            "10.1.2.3",
+            "100.64.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "172.16.0.1",
+            "192.168.1.1",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "64:ff9b::7f00:1",
+            "2002:7f00:1::",
+        ] {
+            assert!(!is_ip_allowed(&policy, ip), "{ip} should be denied");
+        }
+
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_ip_allowed(&policy, ip), "{ip} should be allowed");
+        }
+    }
+
+    #[test]
+    fn checks_ipv4_mapped_addresses_as_ipv4() {
+        let policy = policy();
+
+        assert!(!is_ip_allowed(&policy, "::ffff:127.0.0.1"));
+        assert!(!is_ip_allowed(&policy, "::ffff:169.254.169.254"));
+        assert!(is_ip_allowed(&policy, "::ffff:93.184.216.34"));
+    }
+
+    #[test]
+    fn allowed_ranges_take_precedence_over_denied_ones() {
+        let mut policy = policy();
+        policy.allowed_cidrs = vec!["10.1.2.0/24".parse().unwrap()];
+        policy.denied_cidrs.push("93.184.216.0/24".parse().unwrap());
+
+        assert!(is_ip_allowed(&policy, "10.1.2.3"));
+        assert!(is_ip_allowed(&policy, "::ffff:10.1.2.3"));
+        assert!(!is_ip_allowed(&policy, "10.1.3.1"));
+        assert!(!is_ip_allowed(&policy, "93.184.216.34"));
+    }
+
+    #[tokio::test]
+    async fn checks_the_scheme() {
+        let policy = policy();
+
+        assert!(policy.check("http://93.184.216.34/").await.is_ok());
+        assert!(policy.check("https://93.184.216.34/").await.is_ok());
+        assert!(policy.check("ftp://93.184.216.34/").await.is_err());
+        assert!(policy.check("file:///etc/passwd").await.is_err());
+        assert!(policy.check("not a url").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn checks_the_addresses_of_the_host() {
+        let policy = policy();
+
+        for url in [
+            "http://127.0.0.1/",
+            "http://127.1:8080/",
+            "http://0x7f.0.0.1/",
+            "http://2130706433/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://169.254.169.254/latest/meta-data/",
+        ] {
+            assert!(policy.check(url).await.is_err(), "{url} should be denied");
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_the_host_patterns_before_resolving() {
+        let mut policy = policy();
+        policy.denied_hosts = vec!["*.internal".parse().unwrap()];
+
+        let denied = policy.check("http://db.internal/").await;
+        assert_eq!(denied, Err("host `db.internal` is denied".to_string()));
+
+        policy.denied_hosts = Vec::new();
+        policy.allowed_hosts = vec!["*.example.com".parse().unwrap()];
+
+        let not_allowed = policy.check("http://93.184.216.34/").await;
+        assert_eq!(
+            not_allowed,
+            Err("host `93.184.216.34` is not allowed".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_to_the_checked_addresses() {
+        let policy = policy();
+
+        let addresses = policy.resolve("93.184.216.34", 8080).await;
+        assert_eq!(addresses, Ok(vec!["93.184.216.34:8080".parse().unwrap()]));
+
+        assert!(policy.resolve("[::1]", 80).await.is_err());
+        assert!(policy.resolve("[::ffff:10.0.0.1]", 443).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn only_allows_the_files_of_the_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(root.path()).unwrap();
+        let directory = root.join("upload");
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("index.html"), "").unwrap();
+        std::fs::write(root.join("secret.txt"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("secret.txt"), directory.join("link.txt")).unwrap();
+
+        let file_url = |path: &str| format!("file://{}/{path}", directory.display());
+        let policy = policy();
+
+        let allowed = policy
+            .check_local(&file_url("index.html"), &directory)
+            .await;
+        assert!(allowed.is_ok());
+
+        for path in [
+            "../secret.txt",
+            "%2e%2e/secret.txt",
+            "link.txt",
+            "missing.html",
+        ] {
+            let denied = policy.check_local(&file_url(path), &directory).await;
+            assert!(denied.is_err(), "{path} should be denied");
+        }
+    }
+
+    #[test]
+    fn allows_schemes_of_local_pages_only_with_remote_access() {
+        let mut policy = policy();
+        policy.allowed_schemes.push("wss".to_string());
+
+        assert!(policy.is_scheme_allowed("wss", false));
+        assert!(policy.is_scheme_allowed("wss", true));
+        assert!(!policy.is_scheme_allowed("ws", false));
+
+        policy.local_remote_access = RemoteAccess::Denied;
+        assert!(policy.is_scheme_allowed("wss", false));
+        assert!(!policy.is_scheme_allowed("wss", true));
+    }
+
+    #[tokio::test]
+    async fn applies_the_remote_access_to_remote_urls_of_local_pages() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut policy = policy();
+
+        policy.local_remote_access = RemoteAccess::Denied;
+        let denied = policy
+            .check_local("https://93.184.216.34/", directory.path())
+            .await;
+        assert!(denied.is_err());
+
+        policy.local_remote_access =
+            RemoteAccess::AllowListed(vec!["https://93.184.216.34/*".parse().unwrap()]);
+        let allowed = policy
+            .check_local("https://93.184.216.34/style.css", directory.path())
+            .await;
+        assert!(allowed.is_ok());
+        let not_listed = policy
+            .check_local("https://93.184.216.35/style.css", directory.path())
+            .await;
+        assert!(not_listed.is_err());
+
+        // Listed urls must still be allowed by the policy.
+        policy.local_remote_access =
+            RemoteAccess::AllowListed(vec!["http://127.0.0.1/*".parse().unwrap()]);
+        let denied_address = policy
+            .check_local("http://127.0.0.1/style.css", directory.path())
+            .await;
+        assert!(denied_address.is_err());
+    }
+}
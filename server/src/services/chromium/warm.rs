@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::Page;
+
+use super::pool::BrowserPool;
+use super::session::BrowserContextGuard;
+
+/// Browser contexts created ahead of the conversions, each with a blank page, so that creating
+/// them does not add to the latency of the conversions.
+///
+/// A context is handed out to a single conversion, and disposed after it like any other, so that
+/// nothing is shared between conversions. A new context is created in the background to replace
+/// it. Idle contexts count as active ones of their instance, which spreads them across the pool.
+pub struct WarmContextPool {
+    pool: Arc<BrowserPool>,
+    size: usize,
+    contexts: Mutex<VecDeque<(BrowserContextGuard, Page)>>,
+    /// Number of contexts being created.
+    pending: AtomicUsize,
+}
+
+impl WarmContextPool {
+    /// Creates the pool, whose contexts are created in the background. A size of 0 disables it.
+    pub fn new(pool: Arc<BrowserPool>, size: usize) -> Arc<Self> {
+        let warm_contexts = Arc::new(Self {
+            pool,
+            size,
+            contexts: Mutex::new(VecDeque::with_capacity(size)),
+            pending: AtomicUsize::new(0),
+        });
+
+        warm_contexts.replenish();
+
+        warm_contexts
+    }
+
+    /// Takes one of the ready contexts, if any. Contexts of browsers which were relaunched since
+    /// they were created are discarded.
+    pub fn take(self: &Arc<Self>) -> Option<(BrowserContextGuard, Page)> {
+        if self.size == 0 {
+            return None;
+        }
+
+        let warm_context = {
+            let mut contexts = self.contexts.lock().unwrap();
+            std::iter::from_fn(|| contexts.pop_front())
+                .find(|(browser_context, _)| browser_context.is_current())
+        };
+
+        self.replenish();
+
+        warm_context
+    }
+
+    /// Creates the contexts missing from the pool, in the background. Contexts that could not be
+    /// created, such as while a browser is relaunched, are retried on the next call.
+    fn replenish(self: &Arc<Self>) {
+        let ready = self.contexts.lock().unwrap().len();
+        let pending = self.pending.load(Ordering::Relaxed);
+        let missing = self.size.saturating_sub(ready + pending);
+
+        for _ in 0..missing {
+            self.pending.fetch_add(1, Ordering::Relaxed);
+
+            let warm_contexts = self.clone();
+            tokio::spawn(async move {
+                let result = match warm_contexts.pool.acquire() {
+                    Ok(browser) => BrowserContextGuard::create(browser).await,
+                    Err(err) => Err(err.into()),
+                };
+                warm_contexts.pending.fetch_sub(1, Ordering::Relaxed);
+
+                match result {
+                    Ok(warm_context) => warm_contexts
+                        .contexts
+                        .lock()
+                        .unwrap()
+                        .push_back(warm_context),
+                    Err(err) => tracing::warn!("could not create warm browser context: {err}"),
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use pulldown_cmark::{html, Options, Parser};
+
+/// Characters that are not allowed in, or have a meaning in, the path of a relative url.
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+pub const CONTENT_PLACEHOLDER: &str = "{{ content }}";
+const STYLESHEETS_PLACEHOLDER: &str = "{{ stylesheets }}";
+
+const DEFAULT_TEMPLATE: &str = r#"<!doctype html>
+<html>
+    <head>
+        <meta charset="utf-8">
+        <style>
+            body { font-family: sans-serif; line-height: 1.5; }
+            table { border-collapse: collapse; }
+            th, td { border: 1px solid #d0d7de; padding: 6px 13px; }
+            pre { background-color: #f6f8fa; padding: 16px; overflow: auto; }
+        </style>
+        {{ stylesheets }}
+    </head>
+    <body>
+        {{ content }}
+    </body>
+</html>
+"#;
+
+/// Renders CommonMark to html, with the GFM tables, strikethrough and task lists extensions.
+fn render_markdown(markdown: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let parser = Parser::new_ext(markdown, options);
+
+    let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut rendered, parser);
+
+    rendered
+}
+
+/// Wraps the rendered markdown into an html document. The template must contain a
+/// `{{ content }}` placeholder, and may contain a `{{ stylesheets }}` placeholder which is
+/// replaced with links to the given stylesheets.
+fn render_document(
+    template: Option<&str>,
+    content: &str,
+    stylesheets: &[String],
+) -> anyhow::Result<String> {
+    let template = template.unwrap_or(DEFAULT_TEMPLATE);
+    if !template.contains(CONTENT_PLACEHOLDER) {
+        return Err(anyhow!(
+            "template must contain a {CONTENT_PLACEHOLDER} placeholder"
+        ));
+    }
+
+    let stylesheet_links = stylesheets
+        .iter()
+        .map(|stylesheet| {
+            // Filenames are relative urls, in which characters such as `#` or `%` must be
+            // encoded.
+            let href = utf8_percent_encode(stylesheet, PATH_ENCODE_SET).to_string();
+            format!(
+                r#"<link rel="stylesheet" href="{}">"#,
+                html_escape_attribute(&href)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(template
+        .replace(STYLESHEETS_PLACEHOLDER, &stylesheet_links)
+        .replace(CONTENT_PLACEHOLDER, content))
+}
+
+/// Renders every markdown file of the directory, in their filename's alphabetical order, into an
+/// `index.html` file written in the same directory. Stylesheets found in the directory are linked
+/// in the document.
+pub async fn render_directory(dir: &Path, template: Option<&str>) -> anyhow::Result<()> {
+    let mut markdown_filenames = Vec::new();
+    let mut stylesheets = Vec::new();
+
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let filename = entry.file_name().to_string_lossy().into_owned();
+
+        match Path::new(&filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some("md") => markdown_filenames.push(filename),
+            Some("css") => stylesheets.push(filename),
+            _ => {}
+        }
+    }
+
+    if markdown_filenames.is_empty() {
+        return Err(anyhow!("at least one markdown file must be provided"));
+    }
+
+    markdown_filenames.sort();
+    stylesheets.sort();
+
+    let mut content = String::new();
+    for filename in markdown_filenames {
+        let markdown = tokio::fs::read_to_string(dir.join(filename)).await?;
+        content.push_str(&render_markdown(&markdown));
+    }
+
+    let document = render_document(template, &content, &stylesheets)?;
+    tokio::fs::write(dir.join("index.html"), document).await?;
+
+    Ok(())
+}
+
+fn html_escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_templates_without_a_content_placeholder() {
+        let template = "<html><body>{{ stylesheets }}</body></html>";
+
+        assert!(render_document(Some(template), "<p>content</p>", &[]).is_err());
+    }
+
+    #[test]
+    fn fills_the_template_placeholders() {
+        let template = "<head>{{ stylesheets }}</head><body>{{ content }}</body>";
+        let stylesheets = ["style.css".to_owned()];
+
+        let document = render_document(Some(template), "<p>content</p>", &stylesheets).unwrap();
+
+        assert_eq!(
+            document,
+            r#"<head><link rel="stylesheet" href="style.css"></head><body><p>content</p></body>"#
+        );
+    }
+
+    #[test]
+    fn encodes_stylesheet_filenames() {
+        let stylesheets = [
+            "a#b.css".to_owned(),
+            "a\"b.css".to_owned(),
+            "a&b.css".to_owned(),
+            "a b%.css".to_owned(),
+        ];
+
+        let document =
+            render_document(Some("{{ stylesheets }}{{ content }}"), "", &stylesheets).unwrap();
+
+        assert_eq!(
+            document,
+            [
+                r#"<link rel="stylesheet" href="a%23b.css">"#,
+                r#"<link rel="stylesheet" href="a%22b.css">"#,
+                r#"<link rel="stylesheet" href="a&amp;b.css">"#,
+                r#"<link rel="stylesheet" href="a%20b%25.css">"#,
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn renders_gfm_tables() {
+        let rendered = render_markdown("| a | b |\n|---|---|\n| 1 | 2 |\n");
+
+        assert_eq!(
+            rendered,
+            "<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody>\n\
+             <tr><td>1</td><td>2</td></tr>\n\
+             </tbody></table>\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_markdown_files_in_alphabetical_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.md"), "# Second").unwrap();
+        std::fs::write(dir.path().join("c.md"), "# Third").unwrap();
+        std::fs::write(dir.path().join("a.md"), "# First").unwrap();
+        std::fs::write(dir.path().join("z.css"), "").unwrap();
+        std::fs::write(dir.path().join("y.css"), "").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "# Ignored").unwrap();
+
+        let template = "{{ stylesheets }}\n{{ content }}";
+        render_directory(dir.path(), Some(template)).await.unwrap();
+
+        let document = std::fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert_eq!(
+            document,
+            "<link rel=\"stylesheet\" href=\"y.css\">\n\
+             <link rel=\"stylesheet\" href=\"z.css\">\n\
+             <h1>First</h1>\n<h1>Second</h1>\n<h1>Third</h1>\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_directories_without_markdown_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("style.css"), "").unwrap();
+
+        assert!(render_directory(dir.path(), None).await.is_err());
+        assert!(!dir.path().join("index.html").exists());
+    }
+}
@@ -1,15 +1,23 @@
+pub mod cache;
 pub mod chromium;
 pub mod manipulation;
+pub mod markdown;
+pub mod templates;
 
 use std::sync::Arc;
 
 use axum::{Extension, Router};
-use chromium::ChromiumService;
+use cache::{CacheConfig, RenderCache};
+use chromium::{ChromiumConfig, ChromiumService};
 
 pub async fn register_into_router(mut router: Router) -> anyhow::Result<Router> {
-    let chromium_service = ChromiumService::new().await?;
+    let chromium_service = ChromiumService::new(ChromiumConfig::from_env()?).await?;
 
-    router = router.layer(Extension(Arc::new(chromium_service)));
+    let render_cache = RenderCache::new(CacheConfig::from_env()?).await?;
+
+    router = router
+        .layer(Extension(Arc::new(chromium_service)))
+        .layer(Extension(Arc::new(render_cache)));
 
     Ok(router)
 }
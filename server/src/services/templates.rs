@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+pub const HEADER_TEMPLATE_FILENAME: &str = "header.html";
+pub const FOOTER_TEMPLATE_FILENAME: &str = "footer.html";
+
+pub struct InlinedTemplate {
+    pub html: String,
+    /// Filenames of the files which were inlined into the template.
+    pub inlined_files: Vec<String>,
+}
+
+/// Inlines the resources of an html template, such as a pdf header or footer template, which
+/// Chromium renders without loading any resource. Stylesheet links are replaced with style
+/// elements, and `src` attributes and css `url()` references with data URIs.
+///
+/// References are looked up by filename in `files`, the other ones are left untouched.
+pub async fn inline_resources(
+    template: &str,
+    files: &HashMap<String, PathBuf>,
+) -> anyhow::Result<InlinedTemplate> {
+    let mut inlined_files = Vec::new();
+
+    let mut replacements = Vec::new();
+    for tag in find_tags(template, "link") {
+        let tag_html = &template[tag.clone()];
+        let is_stylesheet =
+            find_attribute_values(tag_html, "rel")
+                .first()
+                .is_some_and(|(_, rel)| {
+                    rel.split_ascii_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+                });
+        if !is_stylesheet {
+            continue;
+        }
+
+        let Some((filename, path)) = find_attribute_values(tag_html, "href")
+            .first()
+            .and_then(|(_, href)| resolve_reference(href, files))
+        else {
+            continue;
+        };
+
+        let stylesheet = tokio::fs::read_to_string(path).await?;
+        let stylesheet = inline_css_urls(&stylesheet, files, &mut inlined_files).await?;
+        replacements.push((tag, format!("<style>{stylesheet}</style>")));
+        inlined_files.push(filename.to_owned());
+    }
+    let html = replace_ranges(template, replacements);
+
+    let mut replacements = Vec::new();
+    for (range, src) in find_attribute_values(&html, "src") {
+        if let Some((filename, path)) = resolve_reference(&src, files) {
+            replacements.push((range, to_data_uri(path).await?));
+            inlined_files.push(filename.to_owned());
+        }
+    }
+    let html = replace_ranges(&html, replacements);
+
+    let html = inline_css_urls(&html, files, &mut inlined_files).await?;
+
+    inlined_files.sort();
+    inlined_files.dedup();
+
+    Ok(InlinedTemplate {
+        html,
+        inlined_files,
+    })
+}
+
+async fn inline_css_urls(
+    text: &str,
+    files: &HashMap<String, PathBuf>,
+    inlined_files: &mut Vec<String>,
+) -> anyhow::Result<String> {
+    let mut replacements = Vec::new();
+    for (range, url) in find_css_urls(text) {
+        if let Some((filename, path)) = resolve_reference(&url, files) {
+            replacements.push((range, to_data_uri(path).await?));
+            inlined_files.push(filename.to_owned());
+        }
+    }
+
+    Ok(replace_ranges(text, replacements))
+}
+
+/// Looks up a relative reference, without its query and fragment, among the files.
+fn resolve_reference<'a>(
+    reference: &str,
+    files: &'a HashMap<String, PathBuf>,
+) -> Option<(&'a str, &'a Path)> {
+    let reference = reference.trim();
+    let reference = reference.strip_prefix("./").unwrap_or(reference);
+    let end = reference.find(['?', '#']).unwrap_or(reference.len());
+
+    files
+        .get_key_value(&reference[..end])
+        .map(|(filename, path)| (filename.as_str(), path.as_path()))
+}
+
+async fn to_data_uri(path: &Path) -> anyhow::Result<String> {
+    let content = tokio::fs::read(path).await?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type(path),
+        BASE64.encode(content)
+    ))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("css") => "text/css",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Ranges of the opening tags with the given name, from their `<` to their `>`.
+fn find_tags(html: &str, name: &str) -> Vec<Range<usize>> {
+    // ASCII lowercasing keeps the byte offsets of the original text.
+    let lowercase = html.to_ascii_lowercase();
+    let opening = format!("<{name}");
+
+    let mut tags = Vec::new();
+    let mut offset = 0;
+    while let Some(index) = lowercase[offset..].find(&opening) {
+        let start = offset + index;
+        let name_end = start + opening.len();
+        offset = name_end;
+
+        if !lowercase[name_end..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(length) = lowercase[name_end..].find('>') else {
+            break;
+        };
+
+        let end = name_end + length + 1;
+        tags.push(start..end);
+        offset = end;
+    }
+
+    tags
+}
+
+/// Values of the attributes with the given name, with the ranges of the values without their
+/// quotes.
+fn find_attribute_values(html: &str, name: &str) -> Vec<(Range<usize>, String)> {
+    let lowercase = html.to_ascii_lowercase();
+    let needle = format!("{name}=");
+
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while let Some(index) = lowercase[offset..].find(&needle) {
+        let start = offset + index;
+        offset = start + needle.len();
+
+        let preceded_by_whitespace = lowercase[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        if !preceded_by_whitespace {
+            continue;
+        }
+
+        let rest = &html[offset..];
+        let range = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => match rest[1..].find(quote) {
+                Some(length) => offset + 1..offset + 1 + length,
+                None => break,
+            },
+            _ => {
+                let length = rest
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                offset..offset + length
+            }
+        };
+
+        values.push((range.clone(), html[range.clone()].to_owned()));
+        offset = range.end;
+    }
+
+    values
+}
+
+/// Values of the css `url()` functions, with the ranges of the values without their quotes.
+fn find_css_urls(css: &str) -> Vec<(Range<usize>, String)> {
+    let lowercase = css.to_ascii_lowercase();
+
+    let mut urls = Vec::new();
+    let mut offset = 0;
+    while let Some(index) = lowercase[offset..].find("url(") {
+        offset += index + "url(".len();
+
+        let rest = &css[offset..];
+        let trimmed = rest.trim_start();
+        let start = offset + rest.len() - trimmed.len();
+
+        let range = match trimmed.chars().next() {
+            Some(quote @ ('"' | '\'')) => match trimmed[1..].find(quote) {
+                Some(length) => start + 1..start + 1 + length,
+                None => break,
+            },
+            _ => match trimmed.find(')') {
+                Some(length) => start..start + trimmed[..length].trim_end().len(),
+                None => break,
+            },
+        };
+
+        urls.push((range.clone(), css[range.clone()].to_owned()));
+        offset = range.end;
+    }
+
+    urls
+}
+
+/// Replaces the given non-overlapping ranges of the text.
+fn replace_ranges(text: &str, mut replacements: Vec<(Range<usize>, String)>) -> String {
+    replacements.sort_by_key(|(range, _)| range.start);
+
+    let mut replaced = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (range, replacement) in replacements {
+        replaced.push_str(&text[offset..range.start]);
+        replaced.push_str(&replacement);
+        offset = range.end;
+    }
+    replaced.push_str(&text[offset..]);
+
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(found: Vec<(Range<usize>, String)>, text: &str) -> Vec<String> {
+        found
+            .into_iter()
+            .map(|(range, value)| {
+                assert_eq!(text[range].to_string(), value);
+                value
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_opening_tags_case_insensitively() {
+        let html = r#"<LINK rel="stylesheet" href="a.css"><linkx><link
+            href='b.css'><p>link</p>"#;
+
+        let tags: Vec<_> = find_tags(html, "link")
+            .into_iter()
+            .map(|range| &html[range])
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                r#"<LINK rel="stylesheet" href="a.css">"#,
+                "<link\n            href='b.css'>"
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_quoted_and_unquoted_attribute_values() {
+        let html = r#"<img src="a.png"><img SRC='b.png'><img src=c.png><img data-src="d.png">"#;
+
+        let found = find_attribute_values(html, "src");
+        assert_eq!(values(found, html), ["a.png", "b.png", "c.png"]);
+    }
+
+    #[test]
+    fn finds_css_urls() {
+        let css =
+            r#"a { background: url(a.png) } b { src: URL( "b.woff" ) } c { x: url('c d.png') }"#;
+
+        let found = find_css_urls(css);
+        assert_eq!(values(found, css), ["a.png", "b.woff", "c d.png"]);
+    }
+
+    #[test]
+    fn stops_at_unterminated_values() {
+        assert!(find_attribute_values(r#"<img src="a.png>"#, "src").is_empty());
+        assert!(find_css_urls("url(a.png").is_empty());
+        assert!(find_tags("<link href=a.css", "link").is_empty());
+    }
+
+    #[test]
+    fn replaces_ranges_in_any_order() {
+        let replaced = replace_ranges(
+            "0123456789",
+            vec![(6..8, "b".to_string()), (1..3, "a".to_string())],
+        );
+        assert_eq!(replaced, "0a345b89");
+    }
+
+    #[test]
+    fn resolves_references_to_uploaded_files_only() {
+        let files = HashMap::from([("logo.png".to_string(), PathBuf::from("/tmp/logo.png"))]);
+
+        for reference in ["logo.png", "./logo.png", " logo.png?v=2", "logo.png#top"] {
+            let (filename, _) = resolve_reference(reference, &files).unwrap();
+            assert_eq!(filename, "logo.png");
+        }
+
+        for reference in ["../logo.png", "/logo.png", "https://example.com/logo.png"] {
+            assert!(resolve_reference(reference, &files).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn inlines_stylesheets_images_and_css_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("style.css"),
+            "p { background: url(bg.png) }",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("bg.png"), "bg").unwrap();
+        std::fs::write(dir.path().join("logo.svg"), "logo").unwrap();
+
+        let files = ["style.css", "bg.png", "logo.svg", "unused.png"]
+            .into_iter()
+            .map(|filename| (filename.to_string(), dir.path().join(filename)))
+            .collect();
+
+        let template = r#"<link rel="stylesheet" href="style.css"><img src="logo.svg">
+            <img src="https://example.com/remote.png">"#;
+        let inlined = inline_resources(template, &files).await.unwrap();
+
+        assert_eq!(
+            inlined.html,
+            "<style>p { background: url(data:image/png;base64,Ymc=) }</style>\
+             <img src=\"data:image/svg+xml;base64,bG9nbw==\">\n            \
+             <img src=\"https://example.com/remote.png\">"
+        );
+        assert_eq!(inlined.inlined_files, ["bg.png", "logo.svg", "style.css"]);
+    }
+}
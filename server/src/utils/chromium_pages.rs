@@ -1,13 +1,21 @@
 use std::time::Duration;
 
 use chromiumoxide::{
-    cdp::browser_protocol::{
-        network::EventLoadingFinished,
-        page::{EventDomContentEventFired, EventLifecycleEvent, EventLoadEventFired},
+    cdp::{
+        browser_protocol::{
+            network::EventLoadingFinished,
+            page::{EventDomContentEventFired, EventLifecycleEvent, EventLoadEventFired},
+        },
+        js_protocol::runtime::EvaluateParams,
     },
     Page,
 };
 use futures::{try_join, FutureExt, StreamExt};
+use tokio::time::Instant;
+
+const CONDITION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time the first evaluation of a condition is given, past the deadline of the condition.
+const MIN_FIRST_EVALUATION_TIME: Duration = Duration::from_secs(1);
 
 pub async fn wait_until_page_fully_loaded_with_bounds(
     page: Page,
@@ -65,3 +73,59 @@ async fn wait_for_loading_finished_event(page: &Page) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Polls the page until an element matches the css selector. Returns `false` if the deadline is
+/// reached first.
+pub async fn wait_for_selector(page: &Page, selector: &str, deadline: Instant) -> bool {
+    let selector = serde_json::to_string(selector).unwrap_or_default();
+    let expression = format!("document.querySelector({selector}) !== null");
+
+    wait_for_expression(page, &expression, deadline).await
+}
+
+/// Polls the page until the javascript expression evaluates to a truthy value, or to a promise
+/// resolving to one. Evaluations that throw or reject are considered falsy. Returns `false` if the
+/// deadline is reached first, which only happens after the expression was evaluated once.
+pub async fn wait_for_expression(page: &Page, expression: &str, deadline: Instant) -> bool {
+    // The truthiness is checked once the promise is resolved, and in the page, since values such
+    // as elements cannot be returned by value.
+    let params = EvaluateParams::builder()
+        .expression(format!(
+            "Promise.resolve(({expression})).then((value) => !!value)"
+        ))
+        .await_promise(true)
+        .return_by_value(true)
+        .build()
+        .expect("expression is set");
+
+    // The first evaluation is completed even if the deadline has passed, such as when waiting for
+    // the page to load used up the page load time, so that a condition which is already met is
+    // not reported as timed out.
+    let mut evaluation_deadline = deadline.max(Instant::now() + MIN_FIRST_EVALUATION_TIME);
+
+    loop {
+        let evaluation = tokio::time::timeout_at(
+            evaluation_deadline,
+            page.evaluate_expression(params.clone()),
+        )
+        .await;
+        evaluation_deadline = deadline;
+
+        match evaluation {
+            Ok(Ok(result)) => {
+                if result.into_value::<bool>().unwrap_or(false) {
+                    return true;
+                }
+            }
+            Ok(Err(_)) => {}
+            Err(_) => return false,
+        }
+
+        let next_poll = Instant::now() + CONDITION_POLL_INTERVAL;
+        if next_poll >= deadline {
+            return false;
+        }
+
+        tokio::time::sleep_until(next_poll).await;
+    }
+}
@@ -0,0 +1,51 @@
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+pub fn parse_env_var<T>(name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = match env::var(name) {
+        Ok(value) => value,
+        Err(env::VarError::NotPresent) => return Ok(None),
+        Err(err) => return Err(anyhow!("invalid value for {name}: {err}")),
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse()
+        .map(Some)
+        .map_err(|err| anyhow!("invalid value for {name}: {err}"))
+}
+
+/// Parses a comma separated list of values. Empty items are ignored.
+pub fn parse_env_list<T>(name: &str) -> anyhow::Result<Option<Vec<T>>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = match env::var(name) {
+        Ok(value) => value,
+        Err(env::VarError::NotPresent) => return Ok(None),
+        Err(err) => return Err(anyhow!("invalid value for {name}: {err}")),
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .map_err(|err| anyhow!("invalid value for {name}: {item}: {err}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map(Some)
+}
@@ -1,2 +1,4 @@
 pub mod chromium_pages;
+pub mod env;
+pub mod multipart;
 pub mod temp_files;
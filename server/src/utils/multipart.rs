@@ -0,0 +1,57 @@
+use std::any::type_name;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use axum::{async_trait, body::Bytes};
+use axum_typed_multipart::{FieldMetadata, TryFromChunks, TypedMultipartError};
+use futures::Stream;
+use serde::de::DeserializeOwned;
+
+/// Multipart field containing a json value.
+pub struct JsonField<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned> TryFromChunks for JsonField<T> {
+    async fn try_from_chunks(
+        chunks: impl Stream<Item = Result<Bytes, TypedMultipartError>> + Send + Sync + Unpin,
+        metadata: FieldMetadata,
+    ) -> Result<Self, TypedMultipartError> {
+        let field_name = metadata.name.clone().unwrap_or_default();
+        let bytes = Bytes::try_from_chunks(chunks, metadata).await?;
+
+        serde_json::from_slice(&bytes).map(Self).map_err(|err| {
+            TypedMultipartError::WrongFieldType {
+                field_name,
+                wanted_type: type_name::<T>().to_string(),
+                source: err.into(),
+            }
+        })
+    }
+}
+
+/// Multipart field parsed using the [FromStr] implementation of its type.
+pub struct FromStrField<T>(pub T);
+
+#[async_trait]
+impl<T> TryFromChunks for FromStrField<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    async fn try_from_chunks(
+        chunks: impl Stream<Item = Result<Bytes, TypedMultipartError>> + Send + Sync + Unpin,
+        metadata: FieldMetadata,
+    ) -> Result<Self, TypedMultipartError> {
+        let field_name = metadata.name.clone().unwrap_or_default();
+        let value = String::try_from_chunks(chunks, metadata).await?;
+
+        value
+            .parse()
+            .map(Self)
+            .map_err(|err: T::Err| TypedMultipartError::WrongFieldType {
+                field_name,
+                wanted_type: type_name::<T>().to_string(),
+                source: anyhow::anyhow!("{err}"),
+            })
+    }
+}
@@ -1,5 +1,5 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 use anyhow::anyhow;
 use axum_typed_multipart::FieldData;
@@ -31,6 +31,15 @@ pub async fn group_temp_file_fields(
             None => return Err(anyhow!("file must have a filename")),
         };
 
+        // The files must stay inside of the directory, which is the only one their page can read.
+        let mut components = Path::new(&filename).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(anyhow!("invalid filename: {filename}"));
+        }
+
         let path = dir.path().join(filename);
         tokio::fs::copy(file_field.contents.path(), path).await?;
     }